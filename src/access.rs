@@ -0,0 +1,113 @@
+use crate::crypto::CryptoManager;
+use crate::storage::{Storage, VaultStorage};
+use crate::types::{AccessLog, AccessRecord, EncryptedData};
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+
+pub const ACCESS_FILE: &str = ".smolcase-access.yml";
+
+/// Records who read which secrets in a small file next to the vault, so
+/// reads never have to rewrite `.smolcase.yml`.
+pub struct AccessTracker;
+
+impl AccessTracker {
+    pub fn load(master_key: &str) -> Result<AccessLog> {
+        Self::load_from(&*Storage::open()?, master_key)
+    }
+
+    fn load_from(storage: &dyn VaultStorage, master_key: &str) -> Result<AccessLog> {
+        match storage
+            .load(ACCESS_FILE)
+            .map_err(|e| anyhow!("Failed to read access log: {}", e))?
+        {
+            Some(content) => Self::parse(&content, master_key),
+            None => Ok(AccessLog::default()),
+        }
+    }
+
+    fn parse(content: &str, master_key: &str) -> Result<AccessLog> {
+        let encrypted: EncryptedData =
+            serde_yaml::from_str(content).map_err(|e| anyhow!("Invalid access log: {}", e))?;
+
+        let data = CryptoManager::decrypt_data_with_salt(&encrypted, master_key)?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    fn render(access_log: &AccessLog, master_key: &str) -> Result<String> {
+        let data = serde_json::to_vec(access_log)?;
+        let encrypted = CryptoManager::encrypt_data_with_salt(&data, master_key)?;
+        serde_yaml::to_string(&encrypted)
+            .map_err(|e| anyhow!("Failed to serialize access log: {}", e))
+    }
+
+    pub fn record(master_key: &str, username: &str, keys: &[String]) -> Result<()> {
+        let storage = Storage::open()?;
+        let _lock = storage.lock()?;
+        let mut access_log = Self::load_from(&*storage, master_key)?;
+        let now = Utc::now().to_rfc3339();
+
+        Self::touch(&mut access_log.users, username, username, &now);
+        for key in keys {
+            Self::touch(&mut access_log.secrets, key, username, &now);
+        }
+
+        let content = Self::render(&access_log, master_key)?;
+        storage
+            .save(ACCESS_FILE, &content)
+            .map_err(|e| anyhow!("Failed to write access log: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Combines two diverged access logs, keeping the most recent read of each entry.
+    pub fn merge(ours: &str, theirs: &str, master_key: &str) -> Result<String> {
+        let mut merged = Self::parse(ours, master_key)?;
+        let theirs = Self::parse(theirs, master_key)?;
+
+        for (records, other) in [
+            (&mut merged.users, theirs.users),
+            (&mut merged.secrets, theirs.secrets),
+        ] {
+            for (name, record) in other {
+                match records.get_mut(&name) {
+                    Some(existing) => {
+                        existing.count = existing.count.max(record.count);
+                        if record.last_access > existing.last_access {
+                            existing.last_access = record.last_access;
+                            existing.last_user = record.last_user;
+                        }
+                    }
+                    None => {
+                        records.insert(name, record);
+                    }
+                }
+            }
+        }
+
+        Self::render(&merged, master_key)
+    }
+
+    /// True when the record is missing or older than `days`.
+    pub fn is_dormant(record: Option<&AccessRecord>, days: i64) -> bool {
+        match record {
+            Some(record) => DateTime::parse_from_rfc3339(&record.last_access)
+                .map(|t| t < Utc::now() - Duration::days(days))
+                .unwrap_or(true),
+            None => true,
+        }
+    }
+
+    fn touch(records: &mut HashMap<String, AccessRecord>, name: &str, username: &str, now: &str) {
+        let record = records
+            .entry(name.to_string())
+            .or_insert_with(|| AccessRecord {
+                last_access: now.to_string(),
+                last_user: username.to_string(),
+                count: 0,
+            });
+        record.last_access = now.to_string();
+        record.last_user = username.to_string();
+        record.count += 1;
+    }
+}
@@ -0,0 +1,601 @@
+use crate::credential_manager::CredentialManager;
+use crate::crypto::{CryptoManager, KEY_SIZE};
+use crate::storage::Storage;
+use crate::types::{AuditEntry, LocalCredentials};
+use crate::ui::UI;
+use anyhow::{Result, anyhow};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const AUDIT_FILE: &str = ".smolcase-audit.log";
+
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AuditHeader {
+    version: u32,
+    salt: String,
+}
+
+/// Last line of the log: the entry count and the hash of the last entry,
+/// authenticated with the log key. Dropping entries from the end leaves a
+/// valid chain, so this is what makes truncation visible.
+#[derive(Debug, Serialize, Deserialize)]
+struct AuditHead {
+    entries: usize,
+    head: String,
+    mac: String,
+}
+
+/// Append-only log of vault operations.
+///
+/// The first line holds the key-derivation salt; every following line is one
+/// entry encrypted with the master key, and the last line is the `AuditHead`.
+/// Entries carry the hash of their predecessor, so removed or edited lines
+/// break the chain, and the head records where the chain ends. Each machine
+/// also remembers how far the log reached when it last wrote or verified it,
+/// so deleting the file or restoring an older copy is caught as well.
+pub struct AuditLog;
+
+impl AuditLog {
+    /// Like `record`, but a failure only warns so the audited command still completes.
+    pub fn record_or_warn(
+        master_key: &str,
+        user: &str,
+        action: &str,
+        keys: &[String],
+        details: Option<String>,
+    ) {
+        if let Err(e) = Self::record(master_key, user, action, keys, details) {
+            UI::warning(&format!("Could not write audit log: {}", e));
+        }
+    }
+
+    /// Appends one entry per secret key, or a single entry when `keys` is empty.
+    pub fn record(
+        master_key: &str,
+        user: &str,
+        action: &str,
+        keys: &[String],
+        details: Option<String>,
+    ) -> Result<()> {
+        let storage = Storage::open()?;
+        let _lock = storage.lock()?;
+        let mut cached_creds = CredentialManager::load_credentials()?;
+
+        let content = storage.load(AUDIT_FILE)?;
+        // Appending writes a fresh head, which would seal in whatever was
+        // removed or changed, so a log that fails verification stays as it is
+        let (entries, mut problems) = match &content {
+            Some(content) => Self::parse(content, master_key)?,
+            None => (Vec::new(), Vec::new()),
+        };
+        problems.extend(Self::check_seen(&entries, &cached_creds)?);
+        if !problems.is_empty() {
+            return Err(anyhow!(
+                "{} failed verification, so nothing was added to it: {}. Run 'smolcase audit verify'.",
+                AUDIT_FILE,
+                problems.join("; ")
+            ));
+        }
+
+        let (mut lines, file_key, mut last_entry, mut count) = if let Some(content) = &content {
+            let (header, entry_lines, _) = Self::split(content);
+            let file_key = Self::file_key(header, master_key)?;
+
+            let mut lines = format!("{}\n", header.unwrap_or_default());
+            for line in &entry_lines {
+                lines.push_str(line.trim());
+                lines.push('\n');
+            }
+            (lines, file_key, entries.last().cloned(), entries.len())
+        } else {
+            let salt = CryptoManager::generate_salt();
+            let audit_header = AuditHeader {
+                version: 1,
+                salt: BASE64.encode(salt),
+            };
+            (
+                format!("{}\n", serde_json::to_string(&audit_header)?),
+                CryptoManager::derive_key_with_salt(master_key, &salt)?,
+                None,
+                0,
+            )
+        };
+
+        let timestamp = Utc::now().to_rfc3339();
+        let host = hostname::get()
+            .map(|h| h.to_string_lossy().to_string())
+            .unwrap_or_else(|_| "unknown".to_string());
+
+        let keys: Vec<Option<String>> = if keys.is_empty() {
+            vec![None]
+        } else {
+            keys.iter().cloned().map(Some).collect()
+        };
+
+        for key in keys {
+            let entry = AuditEntry {
+                seq: last_entry.as_ref().map_or(1, |e| e.seq + 1),
+                timestamp: timestamp.clone(),
+                user: user.to_string(),
+                host: host.clone(),
+                action: action.to_string(),
+                key,
+                details: details.clone(),
+                prev_hash: match &last_entry {
+                    Some(entry) => Self::entry_hash(entry)?,
+                    None => GENESIS_HASH.to_string(),
+                },
+            };
+
+            let encrypted =
+                CryptoManager::encrypt_with_key(&serde_json::to_vec(&entry)?, &file_key)?;
+            lines.push_str(&BASE64.encode(encrypted));
+            lines.push('\n');
+            last_entry = Some(entry);
+            count += 1;
+        }
+        lines.push_str(&Self::head_line(&file_key, count, last_entry.as_ref())?);
+
+        storage
+            .save(AUDIT_FILE, &lines)
+            .map_err(|e| anyhow!("Failed to write audit log: {}", e))?;
+
+        Self::remember(&mut cached_creds, count, last_entry.as_ref())
+    }
+
+    /// Decrypts every entry and returns them with a list of integrity problems.
+    pub fn load(master_key: &str) -> Result<(Vec<AuditEntry>, Vec<String>)> {
+        let mut cached_creds = CredentialManager::load_credentials()?;
+        let (entries, mut problems) = match Storage::open()?.load(AUDIT_FILE)? {
+            Some(content) => Self::parse(&content, master_key)?,
+            None => (Vec::new(), Vec::new()),
+        };
+
+        problems.extend(Self::check_seen(&entries, &cached_creds)?);
+        if problems.is_empty() {
+            Self::remember(&mut cached_creds, entries.len(), entries.last())?;
+        }
+
+        Ok((entries, problems))
+    }
+
+    /// Merges two diverged copies of the log: `theirs` is kept as is and the
+    /// entries added locally since `base` are re-chained on top of it.
+    pub fn merge(base: Option<&str>, ours: &str, theirs: &str, master_key: &str) -> Result<String> {
+        let base_len = match base {
+            Some(base) => Self::parse(base, master_key)?.0.len(),
+            None => 0,
+        };
+        let (our_entries, _) = Self::parse(ours, master_key)?;
+        let (their_entries, _) = Self::parse(theirs, master_key)?;
+        if their_entries.len() < base_len {
+            return Err(anyhow!(
+                "The remote {} has {} entries, fewer than the {} both sides shared; entries were removed from it.",
+                AUDIT_FILE,
+                their_entries.len(),
+                base_len
+            ));
+        }
+        let (header, their_lines, _) = Self::split(theirs);
+        let file_key = Self::file_key(header, master_key)?;
+
+        let mut merged = format!("{}\n", header.unwrap_or_default());
+        for line in &their_lines {
+            merged.push_str(line.trim());
+            merged.push('\n');
+        }
+
+        let mut count = their_lines.len();
+        let mut last_entry = their_entries.last().cloned();
+        for mut entry in our_entries.into_iter().skip(base_len) {
+            entry.seq = last_entry.as_ref().map_or(1, |e| e.seq + 1);
+            entry.prev_hash = match &last_entry {
+                Some(last) => Self::entry_hash(last)?,
+                None => GENESIS_HASH.to_string(),
+            };
+
+            let encrypted =
+                CryptoManager::encrypt_with_key(&serde_json::to_vec(&entry)?, &file_key)?;
+            merged.push_str(&BASE64.encode(encrypted));
+            merged.push('\n');
+            last_entry = Some(entry);
+            count += 1;
+        }
+        merged.push_str(&Self::head_line(&file_key, count, last_entry.as_ref())?);
+
+        Ok(merged)
+    }
+
+    fn parse(content: &str, master_key: &str) -> Result<(Vec<AuditEntry>, Vec<String>)> {
+        let (header, lines, head) = Self::split(content);
+        let file_key = Self::file_key(header, master_key)?;
+
+        let mut entries: Vec<AuditEntry> = Vec::new();
+        let mut problems = Vec::new();
+        let mut expected_hash = GENESIS_HASH.to_string();
+
+        for (index, line) in lines.iter().enumerate() {
+            let line_number = index + 2;
+            let entry = match Self::decrypt_entry(line, &file_key) {
+                Ok(entry) => entry,
+                Err(_) => {
+                    problems.push(format!("line {}: entry cannot be decrypted", line_number));
+                    expected_hash = String::new();
+                    continue;
+                }
+            };
+
+            let expected_seq = entries.last().map_or(1, |e| e.seq + 1);
+            if entry.seq != expected_seq {
+                problems.push(format!(
+                    "line {}: expected entry #{}, found #{}",
+                    line_number, expected_seq, entry.seq
+                ));
+            }
+            if entry.prev_hash != expected_hash {
+                problems.push(format!(
+                    "line {}: entry #{} does not follow the previous entry",
+                    line_number, entry.seq
+                ));
+            }
+
+            expected_hash = Self::entry_hash(&entry)?;
+            entries.push(entry);
+        }
+
+        match head {
+            Some(head) => problems.extend(Self::check_head(
+                head,
+                &file_key,
+                lines.len(),
+                &expected_hash,
+            )),
+            None if !lines.is_empty() => problems.push(
+                "the log has no head record, so entries may have been removed from the end"
+                    .to_string(),
+            ),
+            None => {}
+        }
+
+        Ok((entries, problems))
+    }
+
+    /// Compares the log with how far it reached when this machine last wrote
+    /// or verified it. A merge can move that entry further down, re-chained
+    /// after the remote's entries, but never up and never out of the log.
+    fn check_seen(entries: &[AuditEntry], cached_creds: &LocalCredentials) -> Result<Vec<String>> {
+        let seen = cached_creds.audit_entries;
+        if seen == 0 {
+            return Ok(Vec::new());
+        }
+
+        if entries.len() < seen {
+            return Ok(vec![format!(
+                "the log has {} entries, but this machine already saw {}; it was truncated, deleted or replaced by an older copy",
+                entries.len(),
+                seen
+            )]);
+        }
+
+        if let Some(fingerprint) = &cached_creds.audit_head {
+            for entry in &entries[seen - 1..] {
+                if &Self::fingerprint(entry)? == fingerprint {
+                    return Ok(Vec::new());
+                }
+            }
+            return Ok(vec![format!(
+                "entry #{} that this machine last saw is no longer in the log; it was rewritten or replaced by an older copy",
+                seen
+            )]);
+        }
+
+        Ok(Vec::new())
+    }
+
+    /// Remembers the log's length and last entry in the local credential cache.
+    fn remember(
+        cached_creds: &mut LocalCredentials,
+        entries: usize,
+        last_entry: Option<&AuditEntry>,
+    ) -> Result<()> {
+        let fingerprint = last_entry.map(Self::fingerprint).transpose()?;
+        if cached_creds.audit_entries == entries && cached_creds.audit_head == fingerprint {
+            return Ok(());
+        }
+
+        cached_creds.audit_entries = entries;
+        cached_creds.audit_head = fingerprint;
+        CredentialManager::save_credentials(cached_creds)
+    }
+
+    /// Hash of an entry without its position, which changes when a merge re-chains it.
+    fn fingerprint(entry: &AuditEntry) -> Result<String> {
+        Self::entry_hash(&AuditEntry {
+            seq: 0,
+            prev_hash: String::new(),
+            ..entry.clone()
+        })
+    }
+
+    /// Header line, entry lines and head line (the last line, when it is one).
+    fn split(content: &str) -> (Option<&str>, Vec<&str>, Option<&str>) {
+        let mut lines = content.lines();
+        let header = lines.next();
+        let mut entries: Vec<&str> = lines.filter(|l| !l.trim().is_empty()).collect();
+
+        // Entries are base64, so only the head starts with a brace
+        let head = match entries.last() {
+            Some(line) if line.trim_start().starts_with('{') => entries.pop(),
+            _ => None,
+        };
+
+        (header, entries, head)
+    }
+
+    fn head_line(
+        file_key: &[u8; KEY_SIZE],
+        entries: usize,
+        last_entry: Option<&AuditEntry>,
+    ) -> Result<String> {
+        let head = match last_entry {
+            Some(entry) => Self::entry_hash(entry)?,
+            None => GENESIS_HASH.to_string(),
+        };
+        let mac = Self::head_mac(file_key, entries, &head)
+            .finalize()
+            .into_bytes();
+
+        let head = AuditHead {
+            entries,
+            head,
+            mac: BASE64.encode(mac),
+        };
+        Ok(format!("{}\n", serde_json::to_string(&head)?))
+    }
+
+    fn check_head(
+        line: &str,
+        file_key: &[u8; KEY_SIZE],
+        entries: usize,
+        last_hash: &str,
+    ) -> Vec<String> {
+        let Ok(head) = serde_json::from_str::<AuditHead>(line) else {
+            return vec!["head record cannot be read".to_string()];
+        };
+
+        let authentic = BASE64.decode(&head.mac).is_ok_and(|mac| {
+            Self::head_mac(file_key, head.entries, &head.head)
+                .verify_slice(&mac)
+                .is_ok()
+        });
+        if !authentic {
+            return vec!["head record was altered".to_string()];
+        }
+
+        let mut problems = Vec::new();
+        if head.entries != entries {
+            problems.push(format!(
+                "head record expects {} entries, found {}",
+                head.entries, entries
+            ));
+        }
+        if head.head != last_hash {
+            problems.push("the last entry is not the one the head record points to".to_string());
+        }
+        problems
+    }
+
+    fn head_mac(file_key: &[u8; KEY_SIZE], entries: usize, head: &str) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(file_key).expect("HMAC accepts any key length");
+        mac.update(format!("{}:{}", entries, head).as_bytes());
+        mac
+    }
+
+    fn file_key(header_line: Option<&str>, master_key: &str) -> Result<[u8; KEY_SIZE]> {
+        let header: AuditHeader = serde_json::from_str(
+            header_line.ok_or_else(|| anyhow!("Audit log is missing its header"))?,
+        )
+        .map_err(|e| anyhow!("Invalid audit log header: {}", e))?;
+
+        let salt = BASE64
+            .decode(&header.salt)
+            .map_err(|e| anyhow!("Invalid audit log salt: {}", e))?;
+
+        CryptoManager::derive_key_with_salt(master_key, &salt)
+    }
+
+    fn decrypt_entry(line: &str, file_key: &[u8; KEY_SIZE]) -> Result<AuditEntry> {
+        let data = BASE64
+            .decode(line.trim())
+            .map_err(|e| anyhow!("Invalid audit entry: {}", e))?;
+        let plaintext = CryptoManager::decrypt_with_key(&data, file_key)?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    fn entry_hash(entry: &AuditEntry) -> Result<String> {
+        let digest = Sha256::digest(serde_json::to_vec(entry)?);
+        Ok(digest.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MASTER_KEY: &str = "master-key-for-tests";
+    const SALT: [u8; 16] = [3; 16];
+
+    /// A log holding one entry per action, built the way `record` builds it.
+    fn log(actions: &[&str]) -> String {
+        let header = AuditHeader {
+            version: 1,
+            salt: BASE64.encode(SALT),
+        };
+        let file_key = CryptoManager::derive_key_with_salt(MASTER_KEY, &SALT).unwrap();
+
+        let mut content = format!("{}\n", serde_json::to_string(&header).unwrap());
+        let mut last_entry: Option<AuditEntry> = None;
+        for (index, action) in actions.iter().enumerate() {
+            let entry = AuditEntry {
+                seq: index as u64 + 1,
+                timestamp: format!("2026-01-01T00:00:{:02}Z", index),
+                user: "alice".to_string(),
+                host: "laptop".to_string(),
+                action: action.to_string(),
+                key: None,
+                details: None,
+                prev_hash: match &last_entry {
+                    Some(entry) => AuditLog::entry_hash(entry).unwrap(),
+                    None => GENESIS_HASH.to_string(),
+                },
+            };
+            let encrypted =
+                CryptoManager::encrypt_with_key(&serde_json::to_vec(&entry).unwrap(), &file_key)
+                    .unwrap();
+            content.push_str(&BASE64.encode(encrypted));
+            content.push('\n');
+            last_entry = Some(entry);
+        }
+        content
+            .push_str(&AuditLog::head_line(&file_key, actions.len(), last_entry.as_ref()).unwrap());
+        content
+    }
+
+    fn without_line(content: &str, index: usize) -> String {
+        content
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, line)| format!("{}\n", line))
+            .collect()
+    }
+
+    fn seen(entries: &[AuditEntry]) -> LocalCredentials {
+        LocalCredentials {
+            audit_entries: entries.len(),
+            audit_head: Some(AuditLog::fingerprint(entries.last().unwrap()).unwrap()),
+            ..LocalCredentials::default()
+        }
+    }
+
+    #[test]
+    fn intact_log_verifies() {
+        let (entries, problems) =
+            AuditLog::parse(&log(&["get", "add", "remove"]), MASTER_KEY).unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[2].action, "remove");
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn removed_entry_breaks_the_chain() {
+        let content = without_line(&log(&["get", "add", "remove"]), 2);
+        let (_, problems) = AuditLog::parse(&content, MASTER_KEY).unwrap();
+
+        assert!(
+            problems
+                .iter()
+                .any(|p| p.contains("expected entry #2, found #3"))
+        );
+        assert!(problems.iter().any(|p| p.contains("does not follow")));
+    }
+
+    #[test]
+    fn truncation_is_caught_by_the_head() {
+        // Dropping the last entry leaves a valid chain, but not the one the head sealed
+        let content = without_line(&log(&["get", "add", "remove"]), 3);
+        let (entries, problems) = AuditLog::parse(&content, MASTER_KEY).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert!(
+            problems
+                .iter()
+                .any(|p| p.contains("expects 3 entries, found 2"))
+        );
+    }
+
+    #[test]
+    fn truncation_with_the_head_removed_is_caught() {
+        let content = log(&["get", "add", "remove"]);
+        let content = without_line(&without_line(&content, 4), 3);
+        let (_, problems) = AuditLog::parse(&content, MASTER_KEY).unwrap();
+
+        assert!(problems.iter().any(|p| p.contains("no head record")));
+    }
+
+    #[test]
+    fn head_needs_the_log_key() {
+        let content = without_line(&log(&["get", "add", "remove"]), 3);
+        let mut lines: Vec<&str> = content.lines().collect();
+        let entries: Vec<AuditEntry> = AuditLog::parse(&content, MASTER_KEY).unwrap().0;
+        let forged = AuditLog::head_line(&[9; KEY_SIZE], 2, entries.last()).unwrap();
+        lines.pop();
+        let content = format!("{}\n{}", lines.join("\n"), forged);
+
+        let (_, problems) = AuditLog::parse(&content, MASTER_KEY).unwrap();
+        assert_eq!(problems, vec!["head record was altered".to_string()]);
+    }
+
+    #[test]
+    fn remembered_position_catches_older_copies() {
+        let (entries, _) = AuditLog::parse(&log(&["get", "add", "remove"]), MASTER_KEY).unwrap();
+        let cached_creds = seen(&entries);
+
+        assert!(
+            AuditLog::check_seen(&entries, &cached_creds)
+                .unwrap()
+                .is_empty()
+        );
+
+        // A shorter log that verifies on its own, e.g. an older commit or a deleted file
+        let (older, problems) = AuditLog::parse(&log(&["get", "add"]), MASTER_KEY).unwrap();
+        assert!(problems.is_empty());
+        assert_eq!(
+            AuditLog::check_seen(&older, &cached_creds).unwrap().len(),
+            1
+        );
+        assert_eq!(AuditLog::check_seen(&[], &cached_creds).unwrap().len(), 1);
+
+        // Same length, different history
+        let (rewritten, _) = AuditLog::parse(&log(&["get", "add", "export"]), MASTER_KEY).unwrap();
+        assert!(
+            AuditLog::check_seen(&rewritten, &cached_creds).unwrap()[0]
+                .contains("entry #3 that this machine last saw")
+        );
+    }
+
+    #[test]
+    fn remembered_entry_may_move_down_in_a_merge() {
+        let base = log(&["get"]);
+        let ours = log(&["get", "add"]);
+        let theirs = log(&["get", "remove", "export"]);
+        let (our_entries, _) = AuditLog::parse(&ours, MASTER_KEY).unwrap();
+
+        let merged = AuditLog::merge(Some(&base), &ours, &theirs, MASTER_KEY).unwrap();
+        let (entries, problems) = AuditLog::parse(&merged, MASTER_KEY).unwrap();
+
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(entries[3].action, "add");
+        assert!(
+            AuditLog::check_seen(&entries, &seen(&our_entries))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn merge_rejects_a_remote_log_missing_shared_entries() {
+        let base = log(&["get", "add"]);
+        let ours = log(&["get", "add", "remove"]);
+        let theirs = log(&["get"]);
+
+        assert!(AuditLog::merge(Some(&base), &ours, &theirs, MASTER_KEY).is_err());
+    }
+}
@@ -5,6 +5,9 @@ use std::fs;
 use std::path::Path;
 use uuid::Uuid;
 
+use crate::audit_log::AuditLog;
+use crate::commands::group::ensure_known;
+use crate::commands::sync::auto_commit;
 use crate::config::ConfigManager;
 use crate::credential_manager::CredentialManager;
 use crate::crypto::CryptoManager;
@@ -16,6 +19,8 @@ pub async fn execute(
     value: Option<String>,
     users: Option<String>,
     groups: Option<String>,
+    tags: Option<String>,
+    no_commit: bool,
 ) -> Result<()> {
     let public_config = ConfigManager::load_public_config()?;
     let cached_creds = CredentialManager::load_credentials()?;
@@ -33,6 +38,10 @@ pub async fn execute(
 
     let master_key = CredentialManager::get_master_key(&cached_creds)?;
     let (_, mut private_config) = ConfigManager::load_full_config(&master_key)?;
+    let actor = cached_creds
+        .username
+        .clone()
+        .unwrap_or_else(|| "admin".to_string());
 
     let is_file = Path::new(&key).exists();
     let secret_key = if is_file {
@@ -69,6 +78,13 @@ pub async fn execute(
             .collect();
     }
 
+    ensure_known("user", &permissions.users, |u| {
+        private_config.users.contains_key(u)
+    })?;
+    ensure_known("group", &permissions.groups, |g| {
+        private_config.groups.contains_key(g)
+    })?;
+
     if permissions.users.is_empty() && permissions.groups.is_empty() {
         UI::info("No permissions specified. This secret will be accessible to all users.");
         if !UI::confirm("Continue?")? {
@@ -81,10 +97,23 @@ pub async fn execute(
         key: secret_key.clone(),
         created_at: Utc::now().to_rfc3339(),
         updated_at: Utc::now().to_rfc3339(),
-        created_by: cached_creds.username.unwrap_or_else(|| "admin".to_string()),
+        created_by: actor.clone(),
         permissions,
         is_file,
         file_path: if is_file { Some(key.clone()) } else { None },
+        // Updating a value keeps its tags unless new ones are given
+        tags: match tags {
+            Some(tags_str) => tags_str
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            None => private_config
+                .secrets
+                .get(&secret_key)
+                .map(|s| s.tags.clone())
+                .unwrap_or_default(),
+        },
     };
 
     // Load existing encrypted secrets
@@ -105,17 +134,19 @@ pub async fn execute(
         file_content: if is_file { Some(fs::read(&key)?) } else { None },
     };
 
-    if let Some(pos) = existing_secrets
+    let audit_action = if let Some(pos) = existing_secrets
         .secrets
         .iter()
         .position(|s| s.key == secret_key)
     {
         existing_secrets.secrets[pos] = new_secret_value;
         UI::info(&format!("Updated secret: {}", secret_key));
+        "secret.update"
     } else {
         existing_secrets.secrets.push(new_secret_value);
         UI::info(&format!("Added secret: {}", secret_key));
-    }
+        "secret.add"
+    };
 
     // Re-encrypt secret values
     let serialized_secrets = serde_json::to_vec(&existing_secrets)?;
@@ -127,8 +158,21 @@ pub async fn execute(
 
     // Save everything
     ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+    AuditLog::record_or_warn(
+        &master_key,
+        &actor,
+        audit_action,
+        std::slice::from_ref(&secret_key),
+        None,
+    );
 
     UI::success(&format!("Secret '{}' added successfully!", secret_key));
+    auto_commit(
+        &public_config.settings,
+        &master_key,
+        cached_creds.username.as_deref(),
+        no_commit,
+    );
 
     Ok(())
 }
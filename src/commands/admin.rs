@@ -0,0 +1,132 @@
+use crate::AdminAction;
+use crate::audit_log::AuditLog;
+use crate::commands::passwd::{MIN_ADMIN_PASSWORD_LEN, prompt_new_password};
+use crate::config::ConfigManager;
+use crate::credential_manager::CredentialManager;
+use crate::crypto::CryptoManager;
+use crate::signing::VaultSigner;
+use crate::ui::UI;
+use anyhow::{Result, anyhow};
+
+pub async fn execute(action: AdminAction) -> Result<()> {
+    let mut public_config = ConfigManager::load_public_config()?;
+    let cached_creds = CredentialManager::load_credentials()?;
+
+    if !cached_creds.is_admin {
+        return Err(anyhow!(
+            "Only admins can change admin settings. Use 'smolcase configure' to set up admin credentials."
+        ));
+    }
+
+    match action {
+        AdminAction::Passwd => {
+            UI::header("Change admin password");
+
+            let current_password = UI::password("Current admin password")?;
+            if !CryptoManager::verify_password(&current_password, &public_config.admin_key_hash)? {
+                return Err(anyhow!("Invalid admin password"));
+            }
+
+            let master_key = CredentialManager::get_master_key(&cached_creds)?;
+            let (_, mut private_config) = ConfigManager::load_full_config(&master_key)?;
+
+            let new_password = prompt_new_password("New admin password", MIN_ADMIN_PASSWORD_LEN)?;
+            let (admin_key_hash, _) = CryptoManager::hash_password(&new_password)?;
+            public_config.admin_key_hash = admin_key_hash;
+            VaultSigner::rekey(&mut public_config, &current_password, &new_password)?;
+            if VaultSigner::is_signed(&public_config) {
+                public_config.revision += 1;
+                VaultSigner::sign(&mut public_config, &mut private_config, &new_password)?;
+            }
+
+            ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+
+            // save_config updates the cache too, so start from what it wrote
+            let mut cached_creds = CredentialManager::load_credentials()?;
+            if cached_creds.admin_password.is_some() {
+                cached_creds.admin_password = Some(new_password);
+                CredentialManager::save_credentials(&cached_creds)?;
+                UI::info("Updated cached credentials");
+            }
+            let actor = cached_creds.username.as_deref().unwrap_or("admin");
+            AuditLog::record_or_warn(&master_key, actor, "admin.passwd", &[], None);
+
+            UI::success("Admin password changed successfully!");
+            UI::warning("Share the new admin password securely with other admins");
+        }
+
+        AdminAction::Sign => {
+            let admin_password = CredentialManager::get_admin_password(&cached_creds)?;
+            if !CryptoManager::verify_password(&admin_password, &public_config.admin_key_hash)? {
+                return Err(anyhow!("Invalid admin password"));
+            }
+
+            let master_key = CredentialManager::get_master_key(&cached_creds)?;
+            let mut private_config =
+                ConfigManager::decrypt_unverified(&public_config, &master_key)?;
+
+            let signed = VaultSigner::is_signed(&public_config);
+            let rolled_back_groups =
+                ConfigManager::rolled_back_groups(&private_config, &cached_creds);
+            let rolled_back = public_config.revision < cached_creds.vault_revision
+                || !rolled_back_groups.is_empty();
+            let tampered = signed && VaultSigner::verify(&public_config, &private_config).is_err();
+
+            if signed && !tampered && !rolled_back {
+                UI::success(&format!(
+                    "Vault signature is valid (revision {})",
+                    public_config.revision
+                ));
+                return Ok(());
+            }
+
+            if public_config.revision < cached_creds.vault_revision {
+                UI::warning(&format!(
+                    "Vault revision {} is older than revision {} already used on this machine.",
+                    public_config.revision, cached_creds.vault_revision
+                ));
+            }
+            if !rolled_back_groups.is_empty() {
+                UI::warning(&format!(
+                    "Members of group(s) {} are older than the ones already used on this machine.",
+                    rolled_back_groups.join(", ")
+                ));
+            }
+            if tampered {
+                UI::warning("The vault was changed without an admin signature.");
+            }
+            if rolled_back || tampered {
+                UI::info("Review the changes with 'smolcase diff' before signing.");
+                if !UI::confirm("Sign the vault as it is now?")? {
+                    return Ok(());
+                }
+            }
+
+            public_config.revision = public_config.revision.max(cached_creds.vault_revision) + 1;
+            if signed {
+                VaultSigner::sign(&mut public_config, &mut private_config, &admin_password)?;
+            } else {
+                VaultSigner::enable(&mut public_config, &mut private_config, &admin_password)?;
+                UI::info("Created an Ed25519 signing key for this vault");
+            }
+
+            ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+
+            let mut cached_creds = CredentialManager::load_credentials()?;
+            cached_creds.vault_public_key = public_config.admin_public_key.clone();
+            cached_creds.vault_revision = public_config.revision;
+            cached_creds.membership_counters = ConfigManager::membership_counters(&private_config);
+            CredentialManager::save_credentials(&cached_creds)?;
+            let actor = cached_creds.username.as_deref().unwrap_or("admin");
+            AuditLog::record_or_warn(&master_key, actor, "admin.sign", &[], None);
+
+            UI::success(&format!(
+                "Vault signed at revision {}",
+                public_config.revision
+            ));
+            UI::info("Commit .smolcase.yml so teammates verify against the new signature");
+        }
+    }
+
+    Ok(())
+}
@@ -1,22 +1,43 @@
+use crate::ApplyOptions;
+use crate::access::AccessTracker;
+use crate::audit_log::AuditLog;
+use crate::commands::export::matches_env;
 use crate::config::ConfigManager;
 use crate::credential_manager::CredentialManager;
 use crate::crypto::CryptoManager;
+use crate::secret_files::file_content;
+use crate::template::{Template, TemplateSecret};
 use crate::types::EncryptedSecrets;
 use crate::ui::UI;
 use anyhow::{Result, anyhow};
 use regex::Regex;
-use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
-pub async fn execute(
+/// A template and where its output goes (stdout when `None`)
+struct Job {
     template: PathBuf,
-    output: Option<PathBuf>,
-    env: Option<String>,
-) -> Result<()> {
-    if !template.exists() {
-        return Err(anyhow!("Template file not found: {}", template.display()));
-    }
+    target: Option<PathBuf>,
+}
+
+pub async fn execute(options: ApplyOptions) -> Result<()> {
+    let jobs = match (&options.template, &options.dir, &options.out) {
+        (Some(template), _, _) => {
+            if !template.exists() {
+                return Err(anyhow!("Template file not found: {}", template.display()));
+            }
+            if options.check && options.output.is_none() {
+                return Err(anyhow!("--check needs --output to compare against"));
+            }
+            vec![Job {
+                template: template.clone(),
+                target: options.output.clone(),
+            }]
+        }
+        (None, Some(dir), Some(out)) => find_templates(dir, out, &options.pattern)?,
+        _ => return Err(anyhow!("Specify a template file, or --dir and --out")),
+    };
 
     let cached_creds = CredentialManager::load_credentials()?;
     let user_password = CredentialManager::get_user_password(&cached_creds)?;
@@ -24,98 +45,312 @@ pub async fn execute(
 
     let (_, private_config) = ConfigManager::load_full_config(&master_key)?;
 
-    // Find and verify user
-    let mut user_found = false;
-    let mut username = String::new();
+    let username =
+        CredentialManager::authenticate_user(&cached_creds, &private_config, &user_password)?;
+
+    let mut secrets = Vec::new();
 
-    if let Some(cached_username) = &cached_creds.username {
-        if let Some(user) = private_config.users.get(cached_username) {
-            if CryptoManager::verify_password(&user_password, &user.password_hash)? {
-                user_found = true;
-                username = cached_username.clone();
+    if !private_config.encrypted_secrets.is_empty() {
+        let decrypted_data =
+            CryptoManager::decrypt_data_with_salt(&private_config.encrypted_secrets, &master_key)?;
+        let decrypted: EncryptedSecrets = serde_json::from_slice(&decrypted_data)?;
+
+        for secret_value in decrypted.secrets {
+            if let Some(secret_meta) = private_config.secrets.get(&secret_value.key) {
+                let has_permission = private_config.can_access(secret_meta, &username);
+
+                if has_permission && matches_env(&secret_value.key, options.env.as_deref()) {
+                    secrets.push(TemplateSecret {
+                        value: if secret_value.is_file {
+                            file_content(&secret_value)?
+                        } else {
+                            secret_value.value.into_bytes()
+                        },
+                        key: secret_value.key,
+                        tags: secret_meta.tags.clone(),
+                    });
+                }
             }
         }
     }
+    secrets.sort_by(|a, b| a.key.cmp(&b.key));
 
-    if !user_found {
-        for (uname, user) in &private_config.users {
-            if CryptoManager::verify_password(&user_password, &user.password_hash)? {
-                user_found = true;
-                username = uname.clone();
-                break;
+    let mut rendered = Vec::new();
+    let mut used_secrets: Vec<String> = Vec::new();
+    let mut missing = Vec::new();
+    let mut missing_count = 0;
+
+    for job in &jobs {
+        let template_content = fs::read_to_string(&job.template).map_err(|e| {
+            anyhow!(
+                "Failed to read template file {}: {}",
+                job.template.display(),
+                e
+            )
+        })?;
+        let result = Template::parse(&template_content)
+            .and_then(|t| t.render(&secrets))
+            .map_err(|e| anyhow!("{}: {}", job.template.display(), e))?;
+
+        for key in result.used {
+            if !used_secrets.contains(&key) {
+                used_secrets.push(key);
             }
         }
+        missing_count += result.missing.len();
+        if !result.missing.is_empty() {
+            // Say which template wants them when there are several
+            missing.push(match options.dir {
+                Some(_) => format!("{} ({})", result.missing.join(", "), job.template.display()),
+                None => result.missing.join(", "),
+            });
+        }
+        rendered.push((job, result.output));
     }
 
-    if !user_found {
-        return Err(anyhow!("Invalid password"));
+    if !missing.is_empty() {
+        if options.strict {
+            return Err(anyhow!(
+                "Missing secrets: {}. Nothing was written.",
+                missing.join("; ")
+            ));
+        }
+        UI::warning(&format!("Missing secrets: {}", missing.join("; ")));
+        UI::info("These will be left as {{MISSING:SECRET_NAME}} in the output");
     }
 
-    let mut secrets_map = HashMap::new();
+    if options.check {
+        let stale: Vec<&Path> = rendered
+            .iter()
+            .filter_map(|(job, output)| job.target.as_deref().map(|t| (t, output)))
+            .filter(|(target, output)| fs::read_to_string(target).ok().as_ref() != Some(*output))
+            .map(|(target, _)| target)
+            .collect();
 
-    if !private_config.encrypted_secrets.is_empty() {
-        let decrypted_data =
-            CryptoManager::decrypt_data_with_salt(&private_config.encrypted_secrets, &master_key)?;
-        let secrets: EncryptedSecrets = serde_json::from_slice(&decrypted_data)?;
+        for target in &stale {
+            UI::error(&format!("Out of date: {}", target.display()));
+        }
+        if !stale.is_empty() {
+            return Err(anyhow!(
+                "{} rendered file(s) differ from their templates. Run 'smolcase apply' without --check to update them.",
+                stale.len()
+            ));
+        }
+        UI::success(&format!("{} rendered file(s) up to date", rendered.len()));
+        // Only compared, nothing written: no access or audit entries to record
+        return Ok(());
+    }
 
-        for secret_value in &secrets.secrets {
-            if let Some(secret_meta) = private_config.secrets.get(&secret_value.key) {
-                let has_permission = secret_meta.permissions.users.is_empty()
-                    && secret_meta.permissions.groups.is_empty()
-                    || secret_meta.permissions.users.contains(&username)
-                    || secret_meta.permissions.groups.iter().any(|group| {
-                        private_config
-                            .groups
-                            .get(group)
-                            .map_or(false, |g| g.members.contains(&username))
-                    });
+    for (job, output) in &rendered {
+        match &job.target {
+            Some(target) => write_private(target, output)?,
+            None => println!("{}", output),
+        }
+    }
 
-                if has_permission && !secret_value.is_file {
-                    secrets_map.insert(secret_value.key.clone(), secret_value.value.clone());
-                }
+    match (&options.dir, &options.out) {
+        (Some(_), Some(out)) => UI::success(&format!(
+            "Rendered {} template(s) into {}",
+            rendered.len(),
+            out.display()
+        )),
+        _ => {
+            if let Some(output_path) = &options.output {
+                UI::success(&format!("Template applied to {}", output_path.display()));
             }
         }
     }
+    if options.output.is_some() || options.dir.is_some() {
+        if !missing.is_empty() {
+            UI::warning(&format!("{} secrets were missing", missing_count));
+        } else {
+            UI::info(&format!("Substituted {} secrets", used_secrets.len()));
+        }
+    }
 
-    let template_content = fs::read_to_string(&template)
-        .map_err(|e| anyhow!("Failed to read template file: {}", e))?;
+    if let Err(e) = AccessTracker::record(&master_key, &username, &used_secrets) {
+        UI::warning(&format!("Could not record access: {}", e));
+    }
+    AuditLog::record_or_warn(
+        &master_key,
+        &username,
+        "apply",
+        &used_secrets,
+        Some(
+            jobs.iter()
+                .map(|j| j.template.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+    );
 
-    // Replace {{SECRET_NAME}} with actual secret values
-    let re = Regex::new(r"\{\{([A-Za-z0-9_]+)\}\}")
-        .map_err(|e| anyhow!("Failed to create regex: {}", e))?;
+    Ok(())
+}
 
-    let mut missing_secrets = Vec::new();
-    let processed_content = re.replace_all(&template_content, |caps: &regex::Captures| {
-        let secret_name = &caps[1];
-        if let Some(secret_value) = secrets_map.get(secret_name) {
-            secret_value.clone()
-        } else {
-            missing_secrets.push(secret_name.to_string());
-            format!("{{{{MISSING:{}}}}}", secret_name)
+/// Templates under `dir` whose file name (or, for patterns with a `/`,
+/// relative path) matches `pattern`, each with its place under `out`.
+fn find_templates(dir: &Path, out: &Path, pattern: &str) -> Result<Vec<Job>> {
+    if !dir.is_dir() {
+        return Err(anyhow!("Template directory not found: {}", dir.display()));
+    }
+
+    let matcher = glob_regex(pattern)?;
+    let mut files = Vec::new();
+    walk(dir, out, &mut files)?;
+    files.sort();
+
+    let jobs: Vec<Job> = files
+        .into_iter()
+        .filter_map(|file| {
+            let relative = file.strip_prefix(dir).ok()?.to_path_buf();
+            let relative_str = relative.to_string_lossy().replace('\\', "/");
+            let subject = if pattern.contains('/') {
+                relative_str.as_str()
+            } else {
+                relative_str.rsplit('/').next().unwrap_or_default()
+            };
+            if !matcher.is_match(subject) {
+                return None;
+            }
+
+            let name = relative.file_name()?.to_string_lossy().to_string();
+            Some(Job {
+                target: Some(out.join(relative.with_file_name(output_name(&name)))),
+                template: file,
+            })
+        })
+        .collect();
+
+    if jobs.is_empty() {
+        return Err(anyhow!(
+            "No templates matching '{}' in {}",
+            pattern,
+            dir.display()
+        ));
+    }
+    Ok(jobs)
+}
+
+/// Every file under `dir`, skipping `.git` and the output directory.
+fn walk(dir: &Path, out: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).map_err(|e| anyhow!("Cannot read {}: {}", dir.display(), e))? {
+        let path = entry?.path();
+        if path.file_name().is_some_and(|n| n == ".git") || same_path(&path, out) {
+            continue;
         }
-    });
 
-    if !missing_secrets.is_empty() {
-        UI::warning(&format!("Missing secrets: {}", missing_secrets.join(", ")));
-        UI::info("These will be left as {{MISSING:SECRET_NAME}} in the output");
+        if path.is_dir() {
+            walk(&path, out, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
     }
+    Ok(())
+}
 
-    let result = processed_content.to_string();
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
 
-    if let Some(output_path) = output {
-        fs::write(&output_path, &result)
-            .map_err(|e| anyhow!("Failed to write output file: {}", e))?;
+/// `app.env.tmpl` renders to `app.env`; names without a template
+/// extension are kept.
+fn output_name(name: &str) -> String {
+    name.strip_suffix(".tmpl")
+        .or_else(|| name.strip_suffix(".template"))
+        .filter(|stem| !stem.is_empty())
+        .unwrap_or(name)
+        .to_string()
+}
 
-        UI::success(&format!("Template applied to {}", output_path.display()));
+/// `*` and `?` stay within one path segment; `**` crosses them.
+fn glob_regex(pattern: &str) -> Result<Regex> {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
 
-        if !missing_secrets.is_empty() {
-            UI::warning(&format!("{} secrets were missing", missing_secrets.len()));
-        } else {
-            UI::info(&format!("Substituted {} secrets", secrets_map.len()));
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
         }
-    } else {
-        println!("{}", result);
     }
+    regex.push('$');
+
+    Regex::new(&regex).map_err(|e| anyhow!("Invalid pattern '{}': {}", pattern, e))
+}
+
+/// Writes via a temporary file in the same directory and renames it into
+/// place, so readers never see a half-written file. The temporary file is
+/// created owner-only, and the rename keeps that.
+fn write_private(path: &Path, content: &str) -> Result<()> {
+    let dir = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    fs::create_dir_all(dir).map_err(|e| anyhow!("Cannot create {}: {}", dir.display(), e))?;
+
+    let mut file = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|e| anyhow!("Failed to write output file {}: {}", path.display(), e))?;
+    file.write_all(content.as_bytes())
+        .map_err(|e| anyhow!("Failed to write output file {}: {}", path.display(), e))?;
+    file.persist(path).map_err(|e| {
+        anyhow!(
+            "Failed to write output file {}: {}",
+            path.display(),
+            e.error
+        )
+    })?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        glob_regex(pattern).unwrap().is_match(path)
+    }
+
+    #[test]
+    fn strips_template_extensions() {
+        assert_eq!(output_name("app.env.tmpl"), "app.env");
+        assert_eq!(output_name("config.yml.template"), "config.yml");
+        assert_eq!(output_name("plain.conf"), "plain.conf");
+        assert_eq!(output_name(".tmpl"), ".tmpl");
+    }
+
+    #[test]
+    fn single_star_stays_in_one_segment() {
+        assert!(matches("*.tmpl", "app.env.tmpl"));
+        assert!(!matches("*.tmpl", "nested/app.env.tmpl"));
+        assert!(matches("config/?.yml", "config/a.yml"));
+        assert!(!matches("config/?.yml", "config/ab.yml"));
+    }
+
+    #[test]
+    fn double_star_crosses_segments() {
+        assert!(matches("**/*.tmpl", "app.tmpl"));
+        assert!(matches("**/*.tmpl", "a/b/app.tmpl"));
+        assert!(matches("k8s/**", "k8s/base/secret.yaml"));
+        assert!(!matches("k8s/**", "other/secret.yaml"));
+    }
+
+    #[test]
+    fn other_characters_are_literal() {
+        assert!(matches("a+b.(1).tmpl", "a+b.(1).tmpl"));
+        assert!(!matches("a.tmpl", "abtmpl"));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::AuditAction;
+use crate::audit_log::AuditLog;
+use crate::config::ConfigManager;
+use crate::credential_manager::CredentialManager;
+use crate::crypto::CryptoManager;
+use crate::ui::UI;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use colored::*;
+
+pub async fn execute(action: AuditAction) -> Result<()> {
+    let public_config = ConfigManager::load_public_config()?;
+    let cached_creds = CredentialManager::load_credentials()?;
+
+    if !cached_creds.is_admin {
+        return Err(anyhow!(
+            "Only admins can read the audit log. Use 'smolcase configure' to set up admin credentials."
+        ));
+    }
+
+    let admin_password = CredentialManager::get_admin_password(&cached_creds)?;
+    if !CryptoManager::verify_password(&admin_password, &public_config.admin_key_hash)? {
+        return Err(anyhow!("Invalid admin password"));
+    }
+
+    let master_key = CredentialManager::get_master_key(&cached_creds)?;
+    // Verifies the master key before touching the log
+    ConfigManager::load_full_config(&master_key)?;
+
+    let (entries, problems) = AuditLog::load(&master_key)?;
+
+    match action {
+        AuditAction::Log { user, key, since } => {
+            let since = since.as_deref().map(parse_since).transpose()?;
+
+            let matching: Vec<_> = entries
+                .iter()
+                .filter(|e| user.as_ref().is_none_or(|u| &e.user == u))
+                .filter(|e| key.as_ref().is_none_or(|k| e.key.as_ref() == Some(k)))
+                .filter(|e| {
+                    since.is_none_or(|since| {
+                        DateTime::parse_from_rfc3339(&e.timestamp).is_ok_and(|t| t >= since)
+                    })
+                })
+                .collect();
+
+            if matching.is_empty() {
+                UI::info("No matching audit entries");
+            } else {
+                UI::header("Audit Log");
+                for entry in matching {
+                    let mut line = format!(
+                        "{:>5} {} {}@{} {}",
+                        entry.seq.to_string().dimmed(),
+                        entry.timestamp.dimmed(),
+                        entry.user.cyan(),
+                        entry.host,
+                        entry.action.bold()
+                    );
+                    if let Some(key) = &entry.key {
+                        line.push_str(&format!(" {}", key.yellow()));
+                    }
+                    if let Some(details) = &entry.details {
+                        line.push_str(&format!(" ({})", details.dimmed()));
+                    }
+                    println!("{}", line);
+                }
+            }
+
+            if !problems.is_empty() {
+                println!();
+                UI::warning("Audit log integrity check failed. Run 'smolcase audit verify'.");
+            }
+        }
+
+        AuditAction::Verify => {
+            if problems.is_empty() {
+                UI::success(&format!(
+                    "Audit log intact ({} entries, hash chain verified)",
+                    entries.len()
+                ));
+            } else {
+                for problem in &problems {
+                    UI::error(problem);
+                }
+                return Err(anyhow!(
+                    "Audit log has been modified: {} problem(s) found",
+                    problems.len()
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Accepts an RFC 3339 timestamp, a `YYYY-MM-DD` date, or a relative age like `7d` or `12h`.
+fn parse_since(input: &str) -> Result<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(input) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        && let Some(start_of_day) = date.and_hms_opt(0, 0, 0)
+    {
+        return Ok(start_of_day.and_utc());
+    }
+
+    let (amount, unit) = input.split_at(input.len().saturating_sub(1));
+    let amount: i64 = amount.parse().map_err(|_| {
+        anyhow!(
+            "Invalid --since '{}'. Use YYYY-MM-DD, RFC 3339, or e.g. 7d",
+            input
+        )
+    })?;
+    let age = match unit {
+        "d" => Duration::days(amount),
+        "h" => Duration::hours(amount),
+        "m" => Duration::minutes(amount),
+        _ => {
+            return Err(anyhow!(
+                "Invalid --since '{}'. Use YYYY-MM-DD, RFC 3339, or e.g. 7d",
+                input
+            ));
+        }
+    };
+
+    Ok(Utc::now() - age)
+}
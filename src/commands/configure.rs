@@ -17,7 +17,18 @@ pub async fn execute() -> Result<()> {
     UI::warning("Credentials are encrypted and stored only on this machine.");
 
     let public_config = ConfigManager::load_public_config()?;
-    let mut credentials = LocalCredentials::default();
+    // The pinned signing key, highest revision, membership counters and audit
+    // log position seen outlive re-configuring; dropping them would switch
+    // off signature pinning and rollback checks
+    let previous = CredentialManager::load_credentials()?;
+    let mut credentials = LocalCredentials {
+        vault_public_key: previous.vault_public_key,
+        vault_revision: previous.vault_revision,
+        audit_entries: previous.audit_entries,
+        audit_head: previous.audit_head,
+        membership_counters: previous.membership_counters,
+        ..LocalCredentials::default()
+    };
 
     // Ask if user is admin
     let is_admin = UI::confirm("Are you an admin for this project?")?;
@@ -82,6 +93,7 @@ pub async fn execute() -> Result<()> {
             if !CryptoManager::verify_password(&user_password, &user.password_hash)? {
                 return Err(anyhow!("Invalid user password"));
             }
+            user.ensure_active()?;
         } else {
             return Err(anyhow!("User '{}' not found", username));
         }
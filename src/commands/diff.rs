@@ -0,0 +1,155 @@
+use crate::config::{CONFIG_FILE, ConfigManager};
+use crate::credential_manager::CredentialManager;
+use crate::crypto::CryptoManager;
+use crate::git::GitManager;
+use crate::storage::Storage;
+use crate::types::LocalCredentials;
+use crate::ui::UI;
+use crate::vault_diff::VaultDiff;
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What `--textconv` prints when this checkout can't decrypt the vault.
+const TEXTCONV_PLACEHOLDER: &str =
+    "encrypted smolcase vault (run 'smolcase configure' to see what changed)\n";
+
+pub async fn execute(revs: Vec<String>, textconv: Option<PathBuf>, reveal: bool) -> Result<()> {
+    let public_config = ConfigManager::load_public_config()?;
+    let cached_creds = CredentialManager::load_credentials()?;
+
+    // git textconv: print a readable rendering of the given file instead of a diff
+    if let Some(file) = textconv {
+        return print_textconv(&file, &cached_creds);
+    }
+
+    if !cached_creds.is_admin {
+        return Err(anyhow!(
+            "Only admins can diff the vault. Use 'smolcase configure' to set up admin credentials."
+        ));
+    }
+
+    let admin_password = CredentialManager::get_admin_password(&cached_creds)?;
+    if !CryptoManager::verify_password(&admin_password, &public_config.admin_key_hash)? {
+        return Err(anyhow!("Invalid admin password"));
+    }
+
+    let master_key = CredentialManager::get_master_key(&cached_creds)?;
+
+    let storage = Storage::open()?;
+    let current_dir = std::env::current_dir()?;
+    if storage.uses_git() && !GitManager::is_git_repo(&current_dir) {
+        return Err(anyhow!("Not a Git repository"));
+    }
+
+    let current = if storage.uses_git() {
+        "working tree"
+    } else {
+        "current"
+    };
+    let (old_rev, new_rev) = match revs.as_slice() {
+        [] if storage.uses_git() => ("HEAD", None),
+        [] => ("previous", None),
+        [old] => (old.as_str(), None),
+        [old, new] => (old.as_str(), Some(new.as_str())),
+        _ => return Err(anyhow!("Expected at most two revisions")),
+    };
+
+    let (old_content, new_content) = if storage.uses_git() {
+        let old_content = GitManager::file_at_revision(&current_dir, old_rev, CONFIG_FILE)?;
+        let new_content = match new_rev {
+            Some(rev) => GitManager::file_at_revision(&current_dir, rev, CONFIG_FILE)?,
+            None => ConfigManager::load_raw()?,
+        };
+        (old_content, new_content)
+    } else {
+        // Revisions kept by the storage backend, named by vault revision number
+        let revisions = storage.revisions(None)?;
+        let find = |rev: &str| {
+            revisions
+                .iter()
+                .find(|r| r.id.trim_start_matches('0') == rev.trim_start_matches('0'))
+                .map(|r| r.content.clone())
+                .ok_or_else(|| anyhow!("Unknown revision '{}' in {}", rev, storage.describe()))
+        };
+
+        let old_content = match revs.first() {
+            Some(rev) => find(rev)?,
+            None => revisions.first().and_then(|r| r.parent_content.clone()),
+        };
+        let new_content = match new_rev {
+            Some(rev) => find(rev)?,
+            None => ConfigManager::load_raw()?,
+        };
+        (old_content, new_content)
+    };
+
+    let old = VaultDiff::snapshot_of(old_content.as_deref(), &master_key)
+        .map_err(|e| anyhow!("Cannot decrypt vault at {}: {}", old_rev, e))?;
+    let new = VaultDiff::snapshot_of(new_content.as_deref(), &master_key).map_err(|e| {
+        anyhow!(
+            "Cannot decrypt vault at {}: {}",
+            new_rev.unwrap_or(current),
+            e
+        )
+    })?;
+
+    let changes = VaultDiff::compare(old.as_ref(), new.as_ref(), reveal);
+    if changes.is_empty() {
+        UI::info(&format!(
+            "No vault changes between {} and {}",
+            old_rev,
+            new_rev.unwrap_or(current)
+        ));
+    } else {
+        UI::header(&format!("{} → {}", old_rev, new_rev.unwrap_or(current)));
+        VaultDiff::print(&changes, "");
+    }
+
+    Ok(())
+}
+
+/// git runs textconv filters without a terminal, so this never prompts: it
+/// uses the cached master key once the cached credentials of an active user
+/// or the admin check out, and always shows values as fingerprints.
+fn print_textconv(file: &Path, cached_creds: &LocalCredentials) -> Result<()> {
+    let content = fs::read_to_string(file)
+        .map_err(|e| anyhow!("Failed to read {}: {}", file.display(), e))?;
+
+    let Some(master_key) = cached_master_key(cached_creds) else {
+        print!("{}", TEXTCONV_PLACEHOLDER);
+        return Ok(());
+    };
+
+    match VaultDiff::snapshot(&content, &master_key) {
+        Ok(snapshot) => print!("{}", VaultDiff::render(&snapshot, &master_key, false)),
+        Err(e) => {
+            eprintln!("smolcase: cannot decrypt {}: {}", file.display(), e);
+            print!("{}", content);
+        }
+    }
+    Ok(())
+}
+
+/// The cached master key, if the cached user or admin password is still valid.
+fn cached_master_key(cached_creds: &LocalCredentials) -> Option<String> {
+    let master_key = cached_creds.master_key.clone().filter(|k| !k.is_empty())?;
+    let (public_config, private_config) = ConfigManager::load_full_config(&master_key).ok()?;
+
+    let is_admin = cached_creds.is_admin
+        && cached_creds
+            .admin_password
+            .as_deref()
+            .is_some_and(|password| {
+                CryptoManager::verify_password(password, &public_config.admin_key_hash)
+                    .unwrap_or(false)
+            });
+    let is_user = cached_creds
+        .user_password
+        .as_deref()
+        .is_some_and(|password| {
+            CredentialManager::authenticate_user(cached_creds, &private_config, password).is_ok()
+        });
+
+    (is_admin || is_user).then_some(master_key)
+}
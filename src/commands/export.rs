@@ -1,3 +1,5 @@
+use crate::access::AccessTracker;
+use crate::audit_log::AuditLog;
 use crate::config::ConfigManager;
 use crate::credential_manager::CredentialManager;
 use crate::crypto::CryptoManager;
@@ -16,34 +18,8 @@ pub async fn execute(format: String, output: Option<PathBuf>, env: Option<String
 
     let (_, private_config) = ConfigManager::load_full_config(&master_key)?;
 
-    // Find and verify user
-    let mut user_found = false;
-    let mut username = String::new();
-
-    // Try to use cached username first
-    if let Some(cached_username) = &cached_creds.username {
-        if let Some(user) = private_config.users.get(cached_username) {
-            if CryptoManager::verify_password(&user_password, &user.password_hash)? {
-                user_found = true;
-                username = cached_username.clone();
-            }
-        }
-    }
-
-    // If cached username didn't work, try all users
-    if !user_found {
-        for (uname, user) in &private_config.users {
-            if CryptoManager::verify_password(&user_password, &user.password_hash)? {
-                user_found = true;
-                username = uname.clone();
-                break;
-            }
-        }
-    }
-
-    if !user_found {
-        return Err(anyhow!("Invalid password"));
-    }
+    let username =
+        CredentialManager::authenticate_user(&cached_creds, &private_config, &user_password)?;
 
     let mut accessible_secrets = Vec::new();
 
@@ -54,31 +30,11 @@ pub async fn execute(format: String, output: Option<PathBuf>, env: Option<String
 
         for secret_value in &secrets.secrets {
             if let Some(secret_meta) = private_config.secrets.get(&secret_value.key) {
-                let has_permission = secret_meta.permissions.users.is_empty()
-                    && secret_meta.permissions.groups.is_empty()
-                    || secret_meta.permissions.users.contains(&username)
-                    || secret_meta.permissions.groups.iter().any(|group| {
-                        private_config
-                            .groups
-                            .get(group)
-                            .map_or(false, |g| g.members.contains(&username))
-                    });
+                let has_permission = private_config.can_access(secret_meta, &username);
 
                 if has_permission && !secret_value.is_file {
                     // Filter by environment if specified
-                    if let Some(env_filter) = &env {
-                        if secret_value
-                            .key
-                            .to_lowercase()
-                            .contains(&env_filter.to_lowercase())
-                            || secret_value
-                                .key
-                                .ends_with(&format!("_{}", env_filter.to_uppercase()))
-                        {
-                            accessible_secrets
-                                .push((secret_value.key.clone(), secret_value.value.clone()));
-                        }
-                    } else {
+                    if matches_env(&secret_value.key, env.as_deref()) {
                         accessible_secrets
                             .push((secret_value.key.clone(), secret_value.value.clone()));
                     }
@@ -99,6 +55,8 @@ pub async fn execute(format: String, output: Option<PathBuf>, env: Option<String
         }
     }
 
+    let keys: Vec<String> = accessible_secrets.iter().map(|(k, _)| k.clone()).collect();
+
     let content = match format.as_str() {
         "env" => accessible_secrets
             .iter()
@@ -123,5 +81,27 @@ pub async fn execute(format: String, output: Option<PathBuf>, env: Option<String
         println!("{}", content);
     }
 
+    if let Err(e) = AccessTracker::record(&master_key, &username, &keys) {
+        UI::warning(&format!("Could not record access: {}", e));
+    }
+    AuditLog::record_or_warn(
+        &master_key,
+        &username,
+        "export",
+        &keys,
+        Some(format!("format: {}", format)),
+    );
+
     Ok(())
 }
+
+/// Whether `key` belongs to environment `env` (no filter matches everything).
+pub fn matches_env(key: &str, env: Option<&str>) -> bool {
+    match env {
+        Some(env) => {
+            key.to_lowercase().contains(&env.to_lowercase())
+                || key.ends_with(&format!("_{}", env.to_uppercase()))
+        }
+        None => true,
+    }
+}
@@ -1,3 +1,5 @@
+use crate::access::AccessTracker;
+use crate::audit_log::AuditLog;
 use crate::config::ConfigManager;
 use crate::credential_manager::CredentialManager;
 use crate::crypto::CryptoManager;
@@ -17,48 +19,12 @@ pub async fn execute(key: String) -> Result<()> {
         return Err(anyhow!("Secret '{}' not found", key));
     }
 
-    // Find and verify user
-    let mut user_found = false;
-    let mut username = String::new();
-
-    // Try to use cached username first
-    if let Some(cached_username) = &cached_creds.username {
-        if let Some(user) = private_config.users.get(cached_username) {
-            if CryptoManager::verify_password(&user_password, &user.password_hash)? {
-                user_found = true;
-                username = cached_username.clone();
-            }
-        }
-    }
-
-    // If cached username didn't work, try all users
-    if !user_found {
-        for (uname, user) in &private_config.users {
-            if CryptoManager::verify_password(&user_password, &user.password_hash)? {
-                user_found = true;
-                username = uname.clone();
-                break;
-            }
-        }
-    }
-
-    if !user_found {
-        return Err(anyhow!("Invalid password"));
-    }
+    let username =
+        CredentialManager::authenticate_user(&cached_creds, &private_config, &user_password)?;
 
     // Check permissions
     let secret = &private_config.secrets[&key];
-    let has_permission = secret.permissions.users.is_empty()
-        && secret.permissions.groups.is_empty()
-        || secret.permissions.users.contains(&username)
-        || secret.permissions.groups.iter().any(|group| {
-            private_config
-                .groups
-                .get(group)
-                .map_or(false, |g| g.members.contains(&username))
-        });
-
-    if !has_permission {
+    if !private_config.can_access(secret, &username) {
         return Err(anyhow!("Access denied"));
     }
 
@@ -80,5 +46,11 @@ pub async fn execute(key: String) -> Result<()> {
         return Err(anyhow!("Secret value not found"));
     }
 
+    let keys = [key];
+    if let Err(e) = AccessTracker::record(&master_key, &username, &keys) {
+        UI::warning(&format!("Could not record access: {}", e));
+    }
+    AuditLog::record_or_warn(&master_key, &username, "secret.get", &keys, None);
+
     Ok(())
 }
@@ -1,31 +1,98 @@
 use crate::GroupAction;
+use crate::audit_log::AuditLog;
+use crate::commands::sync::auto_commit;
 use crate::config::ConfigManager;
 use crate::credential_manager::CredentialManager;
 use crate::crypto::CryptoManager;
-use crate::types::Group;
+use crate::signing::VaultSigner;
+use crate::types::{Group, PrivateConfig, SmolcaseConfig};
 use crate::ui::UI;
 use anyhow::{Result, anyhow};
 use chrono::Utc;
 use colored::*;
 use uuid::Uuid;
 
-pub async fn execute(action: GroupAction) -> Result<()> {
+pub async fn execute(action: GroupAction, no_commit: bool) -> Result<()> {
     let public_config = ConfigManager::load_public_config()?;
     let cached_creds = CredentialManager::load_credentials()?;
 
-    if !cached_creds.is_admin {
-        return Err(anyhow!(
-            "Only admins can manage groups. Use 'smolcase configure' to set up admin credentials."
-        ));
-    }
+    let master_key;
+    let mut private_config;
+    let actor;
+    // Set for group managers, who sign membership changes with their own key
+    let mut manager_password = None;
 
-    let admin_password = CredentialManager::get_admin_password(&cached_creds)?;
-    if !CryptoManager::verify_password(&admin_password, &public_config.admin_key_hash)? {
-        return Err(anyhow!("Invalid admin password"));
-    }
+    if cached_creds.is_admin {
+        let admin_password = CredentialManager::get_admin_password(&cached_creds)?;
+        if !CryptoManager::verify_password(&admin_password, &public_config.admin_key_hash)? {
+            return Err(anyhow!("Invalid admin password"));
+        }
+
+        master_key = CredentialManager::get_master_key(&cached_creds)?;
+        (_, private_config) = ConfigManager::load_full_config(&master_key)?;
+        actor = cached_creds
+            .username
+            .clone()
+            .unwrap_or_else(|| "admin".to_string());
+    } else {
+        // Group managers may change membership of the groups delegated to them
+        let Some(group) = managed_group(&action) else {
+            return Err(anyhow!(
+                "Only admins can manage groups. Use 'smolcase configure' to set up admin credentials."
+            ));
+        };
 
-    let master_key = CredentialManager::get_master_key(&cached_creds)?;
-    let (_, mut private_config) = ConfigManager::load_full_config(&master_key)?;
+        let username = CredentialManager::get_username(&cached_creds)?;
+        let user_password = CredentialManager::get_user_password(&cached_creds)?;
+        master_key = CredentialManager::get_master_key(&cached_creds)?;
+        (_, private_config) = ConfigManager::load_full_config(&master_key)?;
+
+        let user = private_config
+            .users
+            .get(&username)
+            .ok_or_else(|| anyhow!("User '{}' not found", username))?;
+        if !CryptoManager::verify_password(&user_password, &user.password_hash)? {
+            return Err(anyhow!("Invalid password"));
+        }
+        user.ensure_active()?;
+
+        if !private_config.can_manage_group(&username, group) {
+            return Err(anyhow!(
+                "User '{}' is not a manager of group '{}'",
+                username,
+                group
+            ));
+        }
+
+        // Nesting hands management of the subgroup to this group's managers, so
+        // only groups the manager already controls may be nested or unnested
+        if let GroupAction::AddGroup { subgroups, .. }
+        | GroupAction::RemoveGroup { subgroups, .. } = &action
+        {
+            // Nesting is admin-signed, since it decides who manages what
+            if VaultSigner::is_signed(&public_config) {
+                return Err(anyhow!(
+                    "Only admins can change group nesting in a signed vault"
+                ));
+            }
+
+            let unmanaged: Vec<&str> = subgroups
+                .iter()
+                .map(|g| g.as_str())
+                .filter(|g| !private_config.can_manage_group(&username, g))
+                .collect();
+            if !unmanaged.is_empty() {
+                return Err(anyhow!(
+                    "User '{}' is not a manager of group(s): {}",
+                    username,
+                    unmanaged.join(", ")
+                ));
+            }
+        }
+        actor = username;
+        manager_password = Some(user_password);
+    }
+    let mutating = !matches!(action, GroupAction::List | GroupAction::Show { .. });
 
     match action {
         GroupAction::Create { name, description } => {
@@ -38,27 +105,47 @@ pub async fn execute(action: GroupAction) -> Result<()> {
                 name: name.clone(),
                 description,
                 members: Vec::new(),
+                subgroups: Vec::new(),
+                managers: Vec::new(),
                 created_at: Utc::now().to_rfc3339(),
+                membership_signature: None,
             };
 
             private_config.groups.insert(name.clone(), group);
             ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(&master_key, &actor, "group.create", &[], Some(name.clone()));
 
             UI::success(&format!("Group '{}' created successfully!", name));
         }
 
-        GroupAction::Delete { name } => {
+        GroupAction::Delete { name, force } => {
             if !private_config.groups.contains_key(&name) {
                 return Err(anyhow!("Group '{}' not found", name));
             }
 
+            let references = private_config.group_references(&name);
+            if !references.is_empty() && !force {
+                UI::warning(&format!("Group '{}' is still referenced:", name));
+                for reference in &references {
+                    println!("   • {}", reference);
+                }
+                return Err(anyhow!(
+                    "Refusing to delete referenced group. Use --force to remove the references too."
+                ));
+            }
+
             if !UI::confirm(&format!("Delete group '{}'?", name))? {
                 return Ok(());
             }
 
             private_config.groups.remove(&name);
+            let changes = private_config.repair_references();
             ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(&master_key, &actor, "group.delete", &[], Some(name.clone()));
 
+            for change in &changes {
+                UI::info(change);
+            }
             UI::success(&format!("Group '{}' deleted successfully!", name));
         }
 
@@ -66,10 +153,20 @@ pub async fn execute(action: GroupAction) -> Result<()> {
             UI::header("Groups");
 
             for (name, group) in &private_config.groups {
+                let nested = if group.subgroups.is_empty() {
+                    String::new()
+                } else {
+                    format!(", includes: {}", group.subgroups.join(", "))
+                };
                 println!(
-                    "👥 {} ({} members)",
+                    "👥 {} ({} members{})",
                     name.cyan(),
-                    group.members.len().to_string().dimmed()
+                    private_config
+                        .effective_members(name)
+                        .len()
+                        .to_string()
+                        .dimmed(),
+                    nested.dimmed()
                 );
                 if let Some(desc) = &group.description {
                     println!("   {}", desc.dimmed());
@@ -82,15 +179,12 @@ pub async fn execute(action: GroupAction) -> Result<()> {
                 return Err(anyhow!("Group '{}' not found", group));
             }
 
+            ensure_known("user", &users, |u| private_config.users.contains_key(u))?;
+
             let mut added_users = Vec::new();
 
             if let Some(group_obj) = private_config.groups.get_mut(&group) {
                 for username in users {
-                    if !private_config.users.contains_key(&username) {
-                        UI::warning(&format!("User '{}' not found, skipping", username));
-                        continue;
-                    }
-
                     if !group_obj.members.contains(&username) {
                         group_obj.members.push(username.clone());
                         added_users.push(username);
@@ -100,7 +194,23 @@ pub async fn execute(action: GroupAction) -> Result<()> {
                 }
             }
 
+            if let Some(password) = &manager_password {
+                sign_membership(
+                    &public_config,
+                    &mut private_config,
+                    &group,
+                    &actor,
+                    password,
+                )?;
+            }
             ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(
+                &master_key,
+                &actor,
+                "group.add_user",
+                &[],
+                Some(format!("{}: {}", group, added_users.join(", "))),
+            );
 
             if !added_users.is_empty() {
                 UI::success(&format!(
@@ -129,7 +239,23 @@ pub async fn execute(action: GroupAction) -> Result<()> {
                 }
             }
 
+            if let Some(password) = &manager_password {
+                sign_membership(
+                    &public_config,
+                    &mut private_config,
+                    &group,
+                    &actor,
+                    password,
+                )?;
+            }
             ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(
+                &master_key,
+                &actor,
+                "group.remove_user",
+                &[],
+                Some(format!("{}: {}", group, removed_users.join(", "))),
+            );
 
             if !removed_users.is_empty() {
                 UI::success(&format!(
@@ -139,7 +265,262 @@ pub async fn execute(action: GroupAction) -> Result<()> {
                 ));
             }
         }
+
+        GroupAction::Delegate { group, users } => {
+            ensure_known("user", &users, |u| private_config.users.contains_key(u))?;
+
+            let group_obj = private_config
+                .groups
+                .get_mut(&group)
+                .ok_or_else(|| anyhow!("Group '{}' not found", group))?;
+
+            let mut added_managers = Vec::new();
+            for username in users {
+                if group_obj.managers.contains(&username) {
+                    UI::warning(&format!("User '{}' already manages this group", username));
+                } else {
+                    group_obj.managers.push(username.clone());
+                    added_managers.push(username);
+                }
+            }
+
+            ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(
+                &master_key,
+                &actor,
+                "group.delegate",
+                &[],
+                Some(format!("{}: {}", group, added_managers.join(", "))),
+            );
+
+            if !added_managers.is_empty() {
+                UI::success(&format!(
+                    "Delegated group '{}' to: {}",
+                    group,
+                    added_managers.join(", ")
+                ));
+                UI::info("Managers can add and remove members of this group and its nested groups");
+            }
+            if VaultSigner::is_signed(&public_config) {
+                for username in &added_managers {
+                    if private_config.users[username].public_key.is_none() {
+                        UI::warning(&format!(
+                            "User '{}' has no signing key yet, so can't sign membership changes. \
+                             Run 'smolcase user reset {}' or send them a new invite.",
+                            username, username
+                        ));
+                    }
+                }
+            }
+        }
+
+        GroupAction::Revoke { group, users } => {
+            let group_obj = private_config
+                .groups
+                .get_mut(&group)
+                .ok_or_else(|| anyhow!("Group '{}' not found", group))?;
+
+            let mut removed_managers = Vec::new();
+            for username in users {
+                if let Some(pos) = group_obj.managers.iter().position(|u| u == &username) {
+                    group_obj.managers.remove(pos);
+                    removed_managers.push(username);
+                } else {
+                    UI::warning(&format!("User '{}' does not manage this group", username));
+                }
+            }
+
+            ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(
+                &master_key,
+                &actor,
+                "group.revoke",
+                &[],
+                Some(format!("{}: {}", group, removed_managers.join(", "))),
+            );
+
+            if !removed_managers.is_empty() {
+                UI::success(&format!(
+                    "Revoked management of '{}' from: {}",
+                    group,
+                    removed_managers.join(", ")
+                ));
+            }
+        }
+
+        GroupAction::Show { name } => {
+            let group_obj = private_config
+                .groups
+                .get(&name)
+                .ok_or_else(|| anyhow!("Group '{}' not found", name))?;
+
+            UI::header(&format!("Group: {}", name));
+
+            if let Some(desc) = &group_obj.description {
+                UI::table_row("Description", desc);
+            }
+            UI::table_row(
+                "Direct members",
+                &list_or_none(group_obj.members.iter().map(|m| m.as_str())),
+            );
+            UI::table_row(
+                "Subgroups",
+                &list_or_none(group_obj.subgroups.iter().map(|g| g.as_str())),
+            );
+            UI::table_row(
+                "Managers",
+                &list_or_none(group_obj.managers.iter().map(|m| m.as_str())),
+            );
+
+            let effective = private_config.effective_members(&name);
+            UI::table_row(
+                "Effective members",
+                &list_or_none(effective.iter().map(|m| m.as_str())),
+            );
+        }
+
+        GroupAction::AddGroup { group, subgroups } => {
+            if !private_config.groups.contains_key(&group) {
+                return Err(anyhow!("Group '{}' not found", group));
+            }
+
+            ensure_known("group", &subgroups, |g| {
+                private_config.groups.contains_key(g)
+            })?;
+
+            let mut added_groups = Vec::new();
+
+            for subgroup in subgroups {
+                if private_config.group_contains(&subgroup, &group) {
+                    return Err(anyhow!(
+                        "Adding '{}' to '{}' would create a cycle",
+                        subgroup,
+                        group
+                    ));
+                }
+
+                if let Some(group_obj) = private_config.groups.get_mut(&group) {
+                    if group_obj.subgroups.contains(&subgroup) {
+                        UI::warning(&format!("Group '{}' already included", subgroup));
+                    } else {
+                        group_obj.subgroups.push(subgroup.clone());
+                        added_groups.push(subgroup);
+                    }
+                }
+            }
+
+            ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(
+                &master_key,
+                &actor,
+                "group.add_group",
+                &[],
+                Some(format!("{}: {}", group, added_groups.join(", "))),
+            );
+
+            if !added_groups.is_empty() {
+                UI::success(&format!(
+                    "Added groups to '{}': {}",
+                    group,
+                    added_groups.join(", ")
+                ));
+            }
+        }
+
+        GroupAction::RemoveGroup { group, subgroups } => {
+            if !private_config.groups.contains_key(&group) {
+                return Err(anyhow!("Group '{}' not found", group));
+            }
+
+            let mut removed_groups = Vec::new();
+
+            if let Some(group_obj) = private_config.groups.get_mut(&group) {
+                for subgroup in subgroups {
+                    if let Some(pos) = group_obj.subgroups.iter().position(|g| g == &subgroup) {
+                        group_obj.subgroups.remove(pos);
+                        removed_groups.push(subgroup);
+                    } else {
+                        UI::warning(&format!("Group '{}' not included", subgroup));
+                    }
+                }
+            }
+
+            ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(
+                &master_key,
+                &actor,
+                "group.remove_group",
+                &[],
+                Some(format!("{}: {}", group, removed_groups.join(", "))),
+            );
+
+            if !removed_groups.is_empty() {
+                UI::success(&format!(
+                    "Removed groups from '{}': {}",
+                    group,
+                    removed_groups.join(", ")
+                ));
+            }
+        }
+    }
+
+    if mutating {
+        auto_commit(
+            &public_config.settings,
+            &master_key,
+            cached_creds.username.as_deref(),
+            no_commit,
+        );
     }
 
     Ok(())
 }
+
+/// Fails with every unknown name at once so nothing dangling is ever stored.
+pub fn ensure_known(kind: &str, names: &[String], exists: impl Fn(&str) -> bool) -> Result<()> {
+    let unknown: Vec<&str> = names
+        .iter()
+        .map(|n| n.as_str())
+        .filter(|n| !exists(n))
+        .collect();
+
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Unknown {}(s): {}", kind, unknown.join(", ")))
+    }
+}
+
+/// Signs the members of `group` as `manager` when the vault is signed.
+fn sign_membership(
+    public_config: &SmolcaseConfig,
+    private_config: &mut PrivateConfig,
+    group: &str,
+    manager: &str,
+    password: &str,
+) -> Result<()> {
+    if !VaultSigner::is_signed(public_config) {
+        return Ok(());
+    }
+    VaultSigner::sign_membership(public_config, private_config, group, manager, password)
+}
+
+fn managed_group(action: &GroupAction) -> Option<&str> {
+    match action {
+        GroupAction::AddUser { group, .. }
+        | GroupAction::RemoveUser { group, .. }
+        | GroupAction::AddGroup { group, .. }
+        | GroupAction::RemoveGroup { group, .. } => Some(group),
+        GroupAction::Show { name } => Some(name),
+        _ => None,
+    }
+}
+
+fn list_or_none<'a>(items: impl Iterator<Item = &'a str>) -> String {
+    let items: Vec<_> = items.collect();
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.join(", ")
+    }
+}
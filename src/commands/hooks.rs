@@ -0,0 +1,91 @@
+use crate::HooksAction;
+use crate::config::{CONFIG_FILE, ConfigManager};
+use crate::git::GitManager;
+use crate::storage::STORAGE_FILE;
+use crate::ui::UI;
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::path::Path;
+
+const HOOK_MARKER: &str = "# Installed by smolcase";
+
+const PRE_COMMIT_HOOK: &str = "#!/bin/sh
+# Installed by smolcase: blocks commits containing plaintext vault secrets.
+# Bypass once with 'git commit --no-verify'.
+exec smolcase scan --staged";
+
+pub async fn execute(action: HooksAction) -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    if !GitManager::is_inside_git_repo(&current_dir) {
+        return Err(anyhow!("Not a Git repository"));
+    }
+
+    let hooks_dir = GitManager::hooks_dir(&current_dir)?;
+    let hook_path = hooks_dir.join("pre-commit");
+    let ours = fs::read_to_string(&hook_path).is_ok_and(|content| content.contains(HOOK_MARKER));
+
+    match action {
+        HooksAction::Install { force, vault } => {
+            if hook_path.exists() && !ours && !force {
+                return Err(anyhow!(
+                    "{} already exists. Use --force to replace it.",
+                    hook_path.display()
+                ));
+            }
+
+            // Outside the vault the hook has to be told where the values come from
+            let script = match vault {
+                Some(vault_dir) => {
+                    let vault_dir = fs::canonicalize(&vault_dir)
+                        .map_err(|e| anyhow!("Cannot open vault {}: {}", vault_dir.display(), e))?;
+                    if !is_vault_dir(&vault_dir) {
+                        return Err(anyhow!("{} is not a smolcase vault", vault_dir.display()));
+                    }
+                    format!(
+                        "{} --vault {}\n",
+                        PRE_COMMIT_HOOK,
+                        shell_quote(&vault_dir.to_string_lossy())
+                    )
+                }
+                None if ConfigManager::is_smolcase_project() => format!("{}\n", PRE_COMMIT_HOOK),
+                None => {
+                    return Err(anyhow!(
+                        "Not a smolcase project. Use --vault DIR so the hook knows which vault's values to block."
+                    ));
+                }
+            };
+
+            fs::create_dir_all(&hooks_dir)?;
+            fs::write(&hook_path, script)?;
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+            }
+
+            UI::success(&format!("Installed {}", hook_path.display()));
+            UI::info("Commits now fail if staged files contain vault secret values");
+        }
+
+        HooksAction::Uninstall => {
+            if !ours {
+                UI::info("No smolcase pre-commit hook installed");
+                return Ok(());
+            }
+
+            fs::remove_file(&hook_path)?;
+            UI::success(&format!("Removed {}", hook_path.display()));
+        }
+    }
+
+    Ok(())
+}
+
+fn is_vault_dir(dir: &Path) -> bool {
+    dir.join(CONFIG_FILE).exists() || dir.join(STORAGE_FILE).exists()
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
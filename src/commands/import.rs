@@ -1,3 +1,5 @@
+use crate::audit_log::AuditLog;
+use crate::commands::sync::auto_commit;
 use crate::config::ConfigManager;
 use crate::credential_manager::CredentialManager;
 use crate::crypto::CryptoManager;
@@ -10,7 +12,7 @@ use std::fs;
 use std::path::PathBuf;
 use uuid::Uuid;
 
-pub async fn execute(file: PathBuf, format: String) -> Result<()> {
+pub async fn execute(file: PathBuf, format: String, no_commit: bool) -> Result<()> {
     let public_config = ConfigManager::load_public_config()?;
     let cached_creds = CredentialManager::load_credentials()?;
 
@@ -71,6 +73,7 @@ pub async fn execute(file: PathBuf, format: String) -> Result<()> {
     };
 
     let mut imported_count = 0;
+    let mut imported_keys = Vec::new();
     let created_by = cached_creds
         .username
         .clone()
@@ -89,6 +92,7 @@ pub async fn execute(file: PathBuf, format: String) -> Result<()> {
             },
             is_file: false,
             file_path: None,
+            tags: Vec::new(),
         };
 
         let secret_value = SecretValue {
@@ -105,6 +109,7 @@ pub async fn execute(file: PathBuf, format: String) -> Result<()> {
         }
 
         private_config.secrets.insert(key.clone(), secret);
+        imported_keys.push(key);
         imported_count += 1;
     }
 
@@ -113,11 +118,24 @@ pub async fn execute(file: PathBuf, format: String) -> Result<()> {
         CryptoManager::encrypt_data_with_salt(&serialized_secrets, &master_key)?;
 
     ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+    AuditLog::record_or_warn(
+        &master_key,
+        &created_by,
+        "secret.import",
+        &imported_keys,
+        Some(file.display().to_string()),
+    );
 
     UI::success(&format!(
         "Imported {} secrets successfully!",
         imported_count
     ));
+    auto_commit(
+        &public_config.settings,
+        &master_key,
+        cached_creds.username.as_deref(),
+        no_commit,
+    );
 
     Ok(())
 }
@@ -1,7 +1,9 @@
+use crate::audit_log::AUDIT_FILE;
 use crate::config::ConfigManager;
 use crate::crypto::CryptoManager;
-use crate::git::GitManager;
-use crate::types::{EncryptedData, PrivateConfig, SmolcaseConfig, User};
+use crate::git::{CommitAuthor, GitManager};
+use crate::signing::VaultSigner;
+use crate::types::{EncryptedData, PrivateConfig, ProjectSettings, SmolcaseConfig, User};
 use crate::ui::UI;
 use anyhow::{Result, anyhow};
 use chrono::Utc;
@@ -53,12 +55,10 @@ pub async fn execute(name: Option<String>, git: bool, non_interactive: bool) ->
     };
 
     let mut git_remote = None;
-    if use_git {
-        if UI::confirm("Add GitHub/GitLab remote now?")? {
-            let remote = UI::input("Repository URL (e.g., https://github.com/user/secrets)")?;
-            if !remote.is_empty() {
-                git_remote = Some(remote);
-            }
+    if use_git && UI::confirm("Add GitHub/GitLab remote now?")? {
+        let remote = UI::input("Repository URL (e.g., https://github.com/user/secrets)")?;
+        if !remote.is_empty() {
+            git_remote = Some(remote);
         }
     }
 
@@ -123,33 +123,45 @@ pub async fn execute(name: Option<String>, git: bool, non_interactive: bool) ->
     let admin_user = User {
         id: Uuid::new_v4(),
         username: admin_username.clone(),
-        email: admin_email,
+        email: admin_email.clone(),
         password_hash,
         salt,
         created_at: Utc::now().to_rfc3339(),
         last_access: None,
         is_admin: true,
+        invite_token_hash: None,
+        expires_at: None,
+        disabled: false,
+        public_key: None,
+        signing_key: None,
     };
 
-    let public_config = SmolcaseConfig {
+    let mut public_config = SmolcaseConfig {
         version: "1.0.0".to_string(),
         project_name: project_name.clone(),
         created_at: Utc::now().to_rfc3339(),
         admin_key_hash,
         master_key_hash,
         encrypted_data: EncryptedData::default(),
+        settings: ProjectSettings::default(),
+        revision: 1,
+        admin_public_key: None,
+        admin_signing_key: None,
+        signature: None,
     };
 
     let mut users = HashMap::new();
     users.insert(admin_username.clone(), admin_user);
 
-    let private_config = PrivateConfig {
+    let mut private_config = PrivateConfig {
         users,
         groups: HashMap::new(),
         secrets: HashMap::new(),
         encrypted_secrets: EncryptedData::default(),
     };
 
+    VaultSigner::enable(&mut public_config, &mut private_config, &admin_password)?;
+
     ConfigManager::create_config_dir()?;
     ConfigManager::save_config(&public_config, &private_config, &master_key)?;
 
@@ -163,21 +175,31 @@ pub async fn execute(name: Option<String>, git: bool, non_interactive: bool) ->
         }
 
         // Create .gitignore
-        let gitignore_content = ".smolcase/credentials.json\n.env\n.env.local\n*.log\n";
+        let gitignore_content = format!(
+            ".smolcase/credentials.json\n.env\n.env.local\n*.log\n!{}\n",
+            AUDIT_FILE
+        );
         std::fs::write(".gitignore", gitignore_content)?;
 
-        GitManager::add_and_commit(&current_dir, "Initial smolcase setup")?;
-
-        if let Some(remote_url) = git_remote {
-            if UI::confirm(&format!("Add remote origin: {}?", remote_url))? {
-                std::process::Command::new("git")
-                    .args(&["remote", "add", "origin", &remote_url])
-                    .output()
-                    .map_err(|e| anyhow!("Failed to add remote: {}", e))?;
-
-                UI::success("Git remote added!");
-                UI::info("Run 'git push -u origin main' to push to remote");
-            }
+        GitManager::add_and_commit(
+            &current_dir,
+            &format!("smolcase: initialize project {}", project_name),
+            Some(&CommitAuthor {
+                name: admin_username.clone(),
+                email: admin_email,
+            }),
+        )?;
+
+        if let Some(remote_url) = git_remote
+            && UI::confirm(&format!("Add remote origin: {}?", remote_url))?
+        {
+            std::process::Command::new("git")
+                .args(["remote", "add", "origin", &remote_url])
+                .output()
+                .map_err(|e| anyhow!("Failed to add remote: {}", e))?;
+
+            UI::success("Git remote added!");
+            UI::info("Run 'smolcase sync' to push to remote");
         }
     }
 
@@ -207,7 +229,7 @@ pub async fn execute(name: Option<String>, git: bool, non_interactive: bool) ->
     println!("   2. Run 'smolcase add KEY value' to add your first secret");
     println!("   3. Run 'smolcase tutorial' for guided walkthrough");
     if use_git {
-        println!("   4. Push to remote: 'git push -u origin main'");
+        println!("   4. Push to remote: 'smolcase sync'");
     }
     println!("   5. Share master key securely with your team");
 
@@ -247,33 +269,45 @@ async fn execute_simple(name: Option<String>, git: bool) -> Result<()> {
     let admin_user = User {
         id: Uuid::new_v4(),
         username: admin_username.clone(),
-        email: admin_email,
+        email: admin_email.clone(),
         password_hash,
         salt,
         created_at: Utc::now().to_rfc3339(),
         last_access: None,
         is_admin: true,
+        invite_token_hash: None,
+        expires_at: None,
+        disabled: false,
+        public_key: None,
+        signing_key: None,
     };
 
-    let public_config = SmolcaseConfig {
+    let mut public_config = SmolcaseConfig {
         version: "1.0.0".to_string(),
         project_name: project_name.clone(),
         created_at: Utc::now().to_rfc3339(),
         admin_key_hash,
         master_key_hash,
         encrypted_data: EncryptedData::default(),
+        settings: ProjectSettings::default(),
+        revision: 1,
+        admin_public_key: None,
+        admin_signing_key: None,
+        signature: None,
     };
 
     let mut users = HashMap::new();
     users.insert(admin_username.clone(), admin_user);
 
-    let private_config = PrivateConfig {
+    let mut private_config = PrivateConfig {
         users,
         groups: HashMap::new(),
         secrets: HashMap::new(),
         encrypted_secrets: EncryptedData::default(),
     };
 
+    VaultSigner::enable(&mut public_config, &mut private_config, &admin_password)?;
+
     ConfigManager::create_config_dir()?;
     ConfigManager::save_config(&public_config, &private_config, &master_key)?;
 
@@ -281,7 +315,14 @@ async fn execute_simple(name: Option<String>, git: bool) -> Result<()> {
         UI::info("Initializing Git repository...");
         let current_dir = std::env::current_dir()?;
         GitManager::init_repo(&current_dir)?;
-        GitManager::add_and_commit(&current_dir, "Initial smolcase setup")?;
+        GitManager::add_and_commit(
+            &current_dir,
+            &format!("smolcase: initialize project {}", project_name),
+            Some(&CommitAuthor {
+                name: admin_username.clone(),
+                email: admin_email,
+            }),
+        )?;
     }
 
     UI::success(&format!(
@@ -0,0 +1,113 @@
+use crate::audit_log::AuditLog;
+use crate::commands::passwd::{MIN_PASSWORD_LEN, prompt_new_password};
+use crate::config::ConfigManager;
+use crate::credential_manager::CredentialManager;
+use crate::crypto::CryptoManager;
+use crate::signing::VaultSigner;
+use crate::types::{EncryptedData, Invite, LocalCredentials};
+use crate::ui::UI;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use std::fs;
+use std::path::PathBuf;
+
+pub async fn execute(invite_path: PathBuf) -> Result<()> {
+    if !ConfigManager::is_smolcase_project() {
+        return Err(anyhow!(
+            "Not a smolcase project. Run 'smolcase join' inside the cloned secrets repository."
+        ));
+    }
+
+    let content = fs::read_to_string(&invite_path)
+        .map_err(|e| anyhow!("Failed to read invite file: {}", e))?;
+    let encrypted_invite: EncryptedData =
+        serde_json::from_str(&content).map_err(|e| anyhow!("Invalid invite file: {}", e))?;
+
+    let passphrase = UI::password("Invite passphrase")?;
+    let invite_data = CryptoManager::decrypt_data_with_salt(&encrypted_invite, &passphrase)
+        .map_err(|_| anyhow!("Invalid passphrase or corrupted invite file"))?;
+    let invite: Invite = serde_json::from_slice(&invite_data)?;
+
+    let public_config = ConfigManager::load_public_config()?;
+    if invite.project_name != public_config.project_name {
+        return Err(anyhow!(
+            "Invite is for project '{}', but this is '{}'",
+            invite.project_name,
+            public_config.project_name
+        ));
+    }
+
+    let expires_at = DateTime::parse_from_rfc3339(&invite.expires_at)
+        .map_err(|e| anyhow!("Invalid invite expiry: {}", e))?;
+    if expires_at < Utc::now() {
+        return Err(anyhow!(
+            "Invite expired on {}. Ask an admin for a new one.",
+            invite.expires_at
+        ));
+    }
+
+    let (_, mut private_config) = ConfigManager::load_full_config(&invite.master_key)?;
+
+    let user = private_config
+        .users
+        .get_mut(&invite.username)
+        .ok_or_else(|| anyhow!("User '{}' not found", invite.username))?;
+
+    let token_matches = match &user.invite_token_hash {
+        Some(hash) => CryptoManager::verify_password(&invite.token, hash)?,
+        None => false,
+    };
+    if !token_matches {
+        return Err(anyhow!(
+            "Invite has already been used or was replaced. Ask an admin for a new one."
+        ));
+    }
+    user.ensure_active()?;
+
+    UI::header(&format!(
+        "Join project: {} as {}",
+        public_config.project_name, invite.username
+    ));
+
+    let password = prompt_new_password("Choose a password", MIN_PASSWORD_LEN)?;
+    let (password_hash, salt) = CryptoManager::hash_password(&password)?;
+
+    user.password_hash = password_hash;
+    user.salt = salt;
+    user.invite_token_hash = None;
+    if let Some(signing_key) = &invite.signing_key {
+        VaultSigner::adopt_invite_key(user, signing_key, &password)?;
+    }
+
+    ConfigManager::save_config(&public_config, &private_config, &invite.master_key)?;
+    AuditLog::record_or_warn(&invite.master_key, &invite.username, "user.join", &[], None);
+
+    // save_config and the audit log may have updated the cache since it was loaded
+    let saved = CredentialManager::load_credentials()?;
+    let credentials = LocalCredentials {
+        admin_password: None,
+        user_password: Some(password),
+        username: Some(invite.username.clone()),
+        master_key: Some(invite.master_key.clone()),
+        is_admin: false,
+        vault_public_key: public_config.admin_public_key.clone(),
+        vault_revision: saved.vault_revision.max(public_config.revision),
+        audit_entries: saved.audit_entries,
+        audit_head: saved.audit_head,
+        membership_counters: saved.membership_counters,
+    };
+    CredentialManager::save_credentials(&credentials)?;
+
+    UI::success(&format!(
+        "Welcome, {}! Credentials cached.",
+        invite.username
+    ));
+    UI::info("Commit and push .smolcase.yml so your new password reaches the team");
+
+    if UI::confirm("Delete the invite file now?")? {
+        fs::remove_file(&invite_path)?;
+        UI::success("Invite file deleted");
+    }
+
+    Ok(())
+}
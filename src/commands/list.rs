@@ -32,7 +32,19 @@ pub async fn execute() -> Result<()> {
                 perms.join(", ")
             };
 
-        println!("{} {} ({})", type_icon, key.cyan(), permissions.dimmed());
+        let tags = if secret.tags.is_empty() {
+            String::new()
+        } else {
+            format!(" [{}]", secret.tags.join(", "))
+        };
+
+        println!(
+            "{} {} ({}){}",
+            type_icon,
+            key.cyan(),
+            permissions.dimmed(),
+            tags.dimmed()
+        );
     }
 
     Ok(())
@@ -0,0 +1,76 @@
+use crate::config::{CONFIG_FILE, ConfigManager};
+use crate::credential_manager::CredentialManager;
+use crate::crypto::CryptoManager;
+use crate::git::GitManager;
+use crate::storage::Storage;
+use crate::ui::UI;
+use crate::vault_diff::VaultDiff;
+use anyhow::{Result, anyhow};
+use colored::*;
+
+pub async fn execute(limit: Option<usize>, reveal: bool) -> Result<()> {
+    if !ConfigManager::is_smolcase_project() {
+        return Err(anyhow!("Not a smolcase project"));
+    }
+
+    let public_config = ConfigManager::load_public_config()?;
+    let cached_creds = CredentialManager::load_credentials()?;
+
+    if !cached_creds.is_admin {
+        return Err(anyhow!(
+            "Only admins can read the vault history. Use 'smolcase configure' to set up admin credentials."
+        ));
+    }
+
+    let admin_password = CredentialManager::get_admin_password(&cached_creds)?;
+    if !CryptoManager::verify_password(&admin_password, &public_config.admin_key_hash)? {
+        return Err(anyhow!("Invalid admin password"));
+    }
+
+    let master_key = CredentialManager::get_master_key(&cached_creds)?;
+    let storage = Storage::open()?;
+
+    if storage.uses_git() && !GitManager::is_git_repo(&std::env::current_dir()?) {
+        return Err(anyhow!("Not a Git repository"));
+    }
+
+    let revisions = storage.revisions(limit)?;
+    if revisions.is_empty() {
+        UI::info(&format!("No saved revisions of {}", CONFIG_FILE));
+        return Ok(());
+    }
+
+    UI::header("Vault History");
+
+    for revision in &revisions {
+        println!(
+            "{} {}",
+            revision.id[..8].yellow(),
+            revision.message.lines().next().unwrap_or("").bold()
+        );
+        println!("   {} {}", revision.author.cyan(), revision.time.dimmed());
+
+        let new = VaultDiff::snapshot_of(revision.content.as_deref(), &master_key);
+        let old = VaultDiff::snapshot_of(revision.parent_content.as_deref(), &master_key);
+
+        match (old, new) {
+            (Ok(old), Ok(new)) => {
+                let changes = VaultDiff::compare(old.as_ref(), new.as_ref(), reveal);
+                if changes.is_empty() {
+                    println!("   {}", "no key-level changes".dimmed());
+                } else {
+                    VaultDiff::print(&changes, "   ");
+                }
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                println!(
+                    "   {}",
+                    format!("cannot decrypt this revision: {}", e).red()
+                );
+            }
+        }
+        println!();
+    }
+
+    Ok(())
+}
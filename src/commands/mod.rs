@@ -1,17 +1,29 @@
 pub mod add;
+pub mod admin;
 pub mod apply;
+pub mod audit;
 pub mod configure;
+pub mod diff;
 pub mod export;
 pub mod get;
 pub mod group;
+pub mod hooks;
 pub mod import;
 pub mod init;
+pub mod join;
 pub mod list;
+pub mod log;
 pub mod logout;
+pub mod passwd;
+pub mod redact;
 pub mod remove;
+pub mod repair;
 pub mod run;
+pub mod scan;
+pub mod settings;
 pub mod setup;
 pub mod status;
+pub mod storage;
 pub mod sync;
 pub mod tutorial;
 pub mod user;
@@ -0,0 +1,81 @@
+use crate::audit_log::AuditLog;
+use crate::config::ConfigManager;
+use crate::credential_manager::CredentialManager;
+use crate::crypto::CryptoManager;
+use crate::signing::VaultSigner;
+use crate::ui::UI;
+use anyhow::{Result, anyhow};
+
+pub async fn execute() -> Result<()> {
+    let public_config = ConfigManager::load_public_config()?;
+    let cached_creds = CredentialManager::load_credentials()?;
+
+    let username = CredentialManager::get_username(&cached_creds)?;
+    let master_key = CredentialManager::get_master_key(&cached_creds)?;
+    let (_, mut private_config) = ConfigManager::load_full_config(&master_key)?;
+
+    let user = private_config
+        .users
+        .get(&username)
+        .ok_or_else(|| anyhow!("User '{}' not found", username))?;
+
+    user.ensure_active()?;
+
+    UI::header(&format!("Change password for {}", username));
+
+    let current_password = UI::password("Current password")?;
+    if !CryptoManager::verify_password(&current_password, &user.password_hash)? {
+        return Err(anyhow!("Invalid password"));
+    }
+
+    let new_password = prompt_new_password("New password", MIN_PASSWORD_LEN)?;
+    let (password_hash, salt) = CryptoManager::hash_password(&new_password)?;
+
+    if let Some(user) = private_config.users.get_mut(&username) {
+        user.password_hash = password_hash;
+        user.salt = salt;
+        VaultSigner::rekey_user(user, &current_password, &new_password)?;
+    }
+
+    ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+
+    // Keep the local cache usable if it belongs to this user. save_config
+    // updates the cache too, so start from what it wrote.
+    let mut cached_creds = CredentialManager::load_credentials()?;
+    if cached_creds.username.as_deref() == Some(username.as_str())
+        && cached_creds.user_password.is_some()
+    {
+        cached_creds.user_password = Some(new_password);
+        CredentialManager::save_credentials(&cached_creds)?;
+        UI::info("Updated cached credentials");
+    }
+    AuditLog::record_or_warn(&master_key, &username, "user.passwd", &[], None);
+
+    UI::success(&format!("Password changed for user '{}'", username));
+    UI::info("Commit .smolcase.yml to share the change with your team");
+
+    Ok(())
+}
+
+/// Minimum length for user passwords and invite passphrases.
+pub const MIN_PASSWORD_LEN: usize = 8;
+/// Minimum length for the admin password, matching `init`.
+pub const MIN_ADMIN_PASSWORD_LEN: usize = 12;
+
+/// Prompts for a new password twice and enforces `min_len`.
+pub fn prompt_new_password(prompt: &str, min_len: usize) -> Result<String> {
+    let password = UI::password(&format!("{} ({}+ characters)", prompt, min_len))?;
+    if password.len() < min_len {
+        return Err(anyhow!(
+            "Password must be at least {} characters long",
+            min_len
+        ));
+    }
+
+    let confirmation = UI::password(&format!("Confirm {}", prompt.to_lowercase()))?;
+    if password != confirmation {
+        return Err(anyhow!("Passwords do not match"));
+    }
+
+    Ok(password)
+}
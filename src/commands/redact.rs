@@ -0,0 +1,52 @@
+use crate::config::ConfigManager;
+use crate::credential_manager::CredentialManager;
+use crate::crypto::CryptoManager;
+use crate::redact::Redactor;
+use crate::types::EncryptedSecrets;
+use anyhow::{Result, anyhow};
+
+pub async fn execute() -> Result<()> {
+    if !ConfigManager::is_smolcase_project() {
+        return Err(anyhow!("Not a smolcase project"));
+    }
+
+    let cached_creds = CredentialManager::load_credentials()?;
+    let user_password = CredentialManager::get_user_password(&cached_creds)?;
+    let master_key = CredentialManager::get_master_key(&cached_creds)?;
+    let (_, private_config) = ConfigManager::load_full_config(&master_key)?;
+
+    let username =
+        CredentialManager::authenticate_user(&cached_creds, &private_config, &user_password)?;
+
+    // Only the caller's secrets: matching a value, or naming its key in the
+    // mask, would tell them about secrets they can't read
+    let secrets = if private_config.encrypted_secrets.is_empty() {
+        Vec::new()
+    } else {
+        let decrypted_data =
+            CryptoManager::decrypt_data_with_salt(&private_config.encrypted_secrets, &master_key)?;
+        let secrets: EncryptedSecrets = serde_json::from_slice(&decrypted_data)?;
+        secrets
+            .secrets
+            .into_iter()
+            .filter(|s| {
+                private_config
+                    .secrets
+                    .get(&s.key)
+                    .is_some_and(|meta| private_config.can_access(meta, &username))
+            })
+            .collect()
+    };
+
+    let redactor = Redactor::new(secrets.iter().map(|s| {
+        (
+            s.key.as_str(),
+            s.file_content.as_deref().unwrap_or(s.value.as_bytes()),
+        )
+    }));
+
+    redactor
+        .pipe(tokio::io::stdin(), tokio::io::stdout())
+        .await
+        .map_err(|e| anyhow!("Failed to redact input: {}", e))
+}
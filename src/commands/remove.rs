@@ -1,3 +1,5 @@
+use crate::audit_log::AuditLog;
+use crate::commands::sync::auto_commit;
 use crate::config::ConfigManager;
 use crate::credential_manager::CredentialManager;
 use crate::crypto::CryptoManager;
@@ -5,7 +7,7 @@ use crate::types::EncryptedSecrets;
 use crate::ui::UI;
 use anyhow::{Result, anyhow};
 
-pub async fn execute(key: String) -> Result<()> {
+pub async fn execute(key: String, no_commit: bool) -> Result<()> {
     let public_config = ConfigManager::load_public_config()?;
     let cached_creds = CredentialManager::load_credentials()?;
 
@@ -49,7 +51,22 @@ pub async fn execute(key: String) -> Result<()> {
 
     ConfigManager::save_config(&public_config, &private_config, &master_key)?;
 
+    let actor = cached_creds.username.as_deref().unwrap_or("admin");
+    AuditLog::record_or_warn(
+        &master_key,
+        actor,
+        "secret.remove",
+        std::slice::from_ref(&key),
+        None,
+    );
+
     UI::success(&format!("Secret '{}' removed successfully!", key));
+    auto_commit(
+        &public_config.settings,
+        &master_key,
+        cached_creds.username.as_deref(),
+        no_commit,
+    );
 
     Ok(())
 }
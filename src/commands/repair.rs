@@ -0,0 +1,55 @@
+use crate::audit_log::AuditLog;
+use crate::config::ConfigManager;
+use crate::credential_manager::CredentialManager;
+use crate::crypto::CryptoManager;
+use crate::ui::UI;
+use anyhow::{Result, anyhow};
+
+pub async fn execute() -> Result<()> {
+    let public_config = ConfigManager::load_public_config()?;
+    let cached_creds = CredentialManager::load_credentials()?;
+
+    if !cached_creds.is_admin {
+        return Err(anyhow!(
+            "Only admins can repair the vault. Use 'smolcase configure' to set up admin credentials."
+        ));
+    }
+
+    let admin_password = CredentialManager::get_admin_password(&cached_creds)?;
+    if !CryptoManager::verify_password(&admin_password, &public_config.admin_key_hash)? {
+        return Err(anyhow!("Invalid admin password"));
+    }
+
+    let master_key = CredentialManager::get_master_key(&cached_creds)?;
+    let (_, mut private_config) = ConfigManager::load_full_config(&master_key)?;
+
+    let changes = private_config.repair_references();
+    if changes.is_empty() {
+        UI::success("No stale references found");
+        return Ok(());
+    }
+
+    UI::header("Stale references");
+    for change in &changes {
+        println!("   • {}", change);
+    }
+
+    if !UI::confirm(&format!("Apply {} fixes?", changes.len()))? {
+        return Ok(());
+    }
+
+    ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+
+    let actor = cached_creds.username.as_deref().unwrap_or("admin");
+    AuditLog::record_or_warn(
+        &master_key,
+        actor,
+        "vault.repair",
+        &[],
+        Some(changes.join("; ")),
+    );
+
+    UI::success(&format!("Repaired {} references", changes.len()));
+
+    Ok(())
+}
@@ -1,18 +1,49 @@
+use crate::RunOptions;
+use crate::access::AccessTracker;
+use crate::audit_log::AuditLog;
+use crate::commands::export::matches_env;
 use crate::config::ConfigManager;
 use crate::credential_manager::CredentialManager;
 use crate::crypto::CryptoManager;
-use crate::types::EncryptedSecrets;
+use crate::git::GitManager;
+use crate::redact::Redactor;
+use crate::secret_files::{SecretFiles, env_name, file_content};
+use crate::types::{EncryptedSecrets, PrivateConfig, SecretValue};
 use crate::ui::UI;
 use anyhow::{Result, anyhow};
 use std::collections::HashMap;
-use std::process::Command;
+use std::io;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Child;
+use tokio::task::JoinHandle;
 
-pub async fn execute(env: Option<String>, command: Vec<String>) -> Result<()> {
-    if command.is_empty() {
+/// How often `--watch` checks the vault and Git HEAD
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A secret on its way into the child's environment
+#[derive(PartialEq)]
+struct Injection {
+    name: String,
+    key: String,
+    value: String,
+    /// Content of a file secret, exposed as the path of a temporary file
+    file: Option<Vec<u8>>,
+    /// Already set in the inherited environment
+    existing: bool,
+}
+
+pub async fn execute(options: RunOptions, command: Vec<String>) -> Result<()> {
+    if command.is_empty() && !options.dry_run {
         return Err(anyhow!(
             "No command specified. Use: smolcase run -- <command>"
         ));
     }
+    if options.exec && cfg!(not(unix)) {
+        return Err(anyhow!("--exec is only supported on Unix"));
+    }
+    let restart_signal = signal_number(options.restart_signal.as_deref().unwrap_or("TERM"))?;
 
     let cached_creds = CredentialManager::load_credentials()?;
     let user_password = CredentialManager::get_user_password(&cached_creds)?;
@@ -20,94 +51,777 @@ pub async fn execute(env: Option<String>, command: Vec<String>) -> Result<()> {
 
     let (_, private_config) = ConfigManager::load_full_config(&master_key)?;
 
-    // Find and verify user
-    let mut user_found = false;
-    let mut username = String::new();
+    let username =
+        CredentialManager::authenticate_user(&cached_creds, &private_config, &user_password)?;
 
-    if let Some(cached_username) = &cached_creds.username {
-        if let Some(user) = private_config.users.get(cached_username) {
-            if CryptoManager::verify_password(&user_password, &user.password_hash)? {
-                user_found = true;
-                username = cached_username.clone();
-            }
-        }
+    let mut injections = resolve(&private_config, &master_key, &username, &options)?;
+
+    if options.dry_run {
+        print_plan(&injections, &options);
+        return Ok(());
     }
 
-    if !user_found {
-        for (uname, user) in &private_config.users {
-            if CryptoManager::verify_password(&user_password, &user.password_hash)? {
-                user_found = true;
-                username = uname.clone();
-                break;
-            }
-        }
+    // Vault values win unless the caller asked to keep what's already set
+    let kept = keep_inherited(&mut injections, &options);
+
+    if options.exec
+        && let Some(injection) = injections.iter().find(|i| i.file.is_some())
+    {
+        return Err(anyhow!(
+            "File secret {} needs smolcase to stay running to remove it. Drop --exec or use --exclude {}.",
+            injection.key,
+            injection.key
+        ));
+    }
+
+    if injections.is_empty() {
+        UI::warning("No accessible secrets found");
+    } else {
+        UI::info(&format!(
+            "Running command with {} secrets",
+            injections.len()
+        ));
     }
+    if kept > 0 {
+        UI::info(&format!(
+            "Kept {} variable(s) already set in the environment",
+            kept
+        ));
+    }
+
+    record_access(&master_key, &username, &injections, &command);
+
+    let program = &command[0];
+    let mut signals =
+        Signals::new().map_err(|e| anyhow!("Failed to set up signal handling: {}", e))?;
+    let mut watcher = options
+        .watch
+        .then(|| Watcher::new(&master_key, &username, &options));
 
-    if !user_found {
-        return Err(anyhow!("Invalid password"));
+    loop {
+        // Removed when this goes out of scope, or explicitly before exiting below
+        let mut files = SecretFiles::default();
+        let mut cmd = build_command(&command, &injections, &options, &mut files)?;
+
+        // Hand the process over to the command, e.g. so it runs as PID 1 in a container
+        #[cfg(unix)]
+        if options.exec {
+            use std::os::unix::process::CommandExt;
+            let e = cmd.exec();
+            return Err(anyhow!("Failed to execute command '{}': {}", program, e));
+        }
+
+        let redactor = options.redact.then(|| {
+            Arc::new(Redactor::new(injections.iter().map(|i| {
+                (
+                    i.key.as_str(),
+                    i.file.as_deref().unwrap_or(i.value.as_bytes()),
+                )
+            })))
+        });
+
+        let outcome = supervise(
+            cmd.into(),
+            &mut signals,
+            watcher.as_mut().map(|w| (w, injections.as_slice())),
+            restart_signal,
+            Duration::from_secs(options.grace_period),
+            redactor,
+        )
+        .await
+        .map_err(|e| anyhow!("Failed to execute command '{}': {}", program, e))?;
+        drop(files);
+
+        match outcome {
+            Outcome::Exited(status) => {
+                if !status.success() {
+                    std::process::exit(exit_code(status));
+                }
+                return Ok(());
+            }
+            Outcome::Restarted(changed) => {
+                UI::info(&format!("Secrets changed, restarted '{}'", program));
+                record_access(&master_key, &username, &changed, &command);
+                injections = changed;
+            }
+            Outcome::Revoked(reason) => {
+                return Err(anyhow!("Stopped '{}': {}", program, reason));
+            }
+        }
     }
+}
 
-    let mut env_vars = HashMap::new();
+/// The vault secrets this run injects, named as they will appear in the
+/// child's environment. Includes variables `--no-override` will keep.
+fn resolve(
+    private_config: &PrivateConfig,
+    master_key: &str,
+    username: &str,
+    options: &RunOptions,
+) -> Result<Vec<Injection>> {
+    let mut selected = Vec::new();
 
     if !private_config.encrypted_secrets.is_empty() {
         let decrypted_data =
-            CryptoManager::decrypt_data_with_salt(&private_config.encrypted_secrets, &master_key)?;
+            CryptoManager::decrypt_data_with_salt(&private_config.encrypted_secrets, master_key)?;
         let secrets: EncryptedSecrets = serde_json::from_slice(&decrypted_data)?;
 
-        for secret_value in &secrets.secrets {
+        for secret_value in secrets.secrets {
             if let Some(secret_meta) = private_config.secrets.get(&secret_value.key) {
-                let has_permission = secret_meta.permissions.users.is_empty()
-                    && secret_meta.permissions.groups.is_empty()
-                    || secret_meta.permissions.users.contains(&username)
-                    || secret_meta.permissions.groups.iter().any(|group| {
-                        private_config
-                            .groups
-                            .get(group)
-                            .map_or(false, |g| g.members.contains(&username))
-                    });
-
-                if has_permission && !secret_value.is_file {
-                    env_vars.insert(secret_value.key.clone(), secret_value.value.clone());
+                let has_permission = private_config.can_access(secret_meta, username);
+
+                if has_permission
+                    && matches_env(&secret_value.key, options.env.as_deref())
+                    && (options.only.is_empty() || listed(&options.only, &secret_value))
+                    && !listed(&options.exclude, &secret_value)
+                {
+                    selected.push(secret_value);
                 }
             }
         }
     }
+    selected.sort_by(|a, b| a.key.cmp(&b.key));
 
-    if env_vars.is_empty() {
-        UI::warning("No accessible secrets found");
-    } else {
-        UI::info(&format!("Running command with {} secrets", env_vars.len()));
+    let missing: Vec<&str> = options
+        .only
+        .iter()
+        .filter(|key| {
+            !selected
+                .iter()
+                .any(|s| listed(std::slice::from_ref(key), s))
+        })
+        .map(String::as_str)
+        .collect();
+    if !missing.is_empty() {
+        UI::warning(&format!(
+            "Not found or not accessible: {}",
+            missing.join(", ")
+        ));
     }
 
-    let program = &command[0];
-    let args = if command.len() > 1 {
-        &command[1..]
+    let inherited: HashMap<String, String> = if options.clean_env {
+        HashMap::new()
     } else {
-        &[]
+        std::env::vars().collect()
     };
 
-    let mut cmd = Command::new(program);
-    cmd.args(args);
+    let mut injections: Vec<Injection> = Vec::new();
+    for secret in selected {
+        let (name, value, file) = if secret.is_file {
+            let name = variable_name(&env_name(&secret.key), options)
+                + options.file_suffix.as_deref().unwrap_or("");
+            (name, String::new(), Some(file_content(&secret)?))
+        } else {
+            (variable_name(&secret.key, options), secret.value, None)
+        };
+        let key = secret.key;
 
-    // Add secrets as environment variables
-    for (key, value) in &env_vars {
-        cmd.env(key, value);
+        if let Some(other) = injections.iter().find(|i| i.name == name) {
+            return Err(anyhow!(
+                "{} and {} would both set {}. Adjust --prefix/--strip-prefix or use --exclude.",
+                other.key,
+                key,
+                name
+            ));
+        }
+        injections.push(Injection {
+            existing: inherited.contains_key(&name),
+            name,
+            key,
+            value,
+            file,
+        });
     }
 
-    // Preserve existing environment
-    cmd.envs(std::env::vars());
+    Ok(injections)
+}
 
-    let status = cmd
-        .status()
-        .map_err(|e| anyhow!("Failed to execute command '{}': {}", program, e))?;
+/// Drops the injections `--no-override` leaves to the inherited environment
+/// and returns how many were dropped.
+fn keep_inherited(injections: &mut Vec<Injection>, options: &RunOptions) -> usize {
+    if !options.no_override {
+        return 0;
+    }
+    let before = injections.len();
+    injections.retain(|i| !i.existing);
+    before - injections.len()
+}
+
+fn record_access(master_key: &str, username: &str, injections: &[Injection], command: &[String]) {
+    let keys: Vec<String> = injections.iter().map(|i| i.key.clone()).collect();
+    if let Err(e) = AccessTracker::record(master_key, username, &keys) {
+        UI::warning(&format!("Could not record access: {}", e));
+    }
+    AuditLog::record_or_warn(master_key, username, "run", &keys, Some(command.join(" ")));
+}
+
+fn build_command(
+    command: &[String],
+    injections: &[Injection],
+    options: &RunOptions,
+    files: &mut SecretFiles,
+) -> Result<Command> {
+    let mut cmd = Command::new(&command[0]);
+    cmd.args(&command[1..]);
+
+    if options.clean_env {
+        cmd.env_clear();
+    }
+    if options.redact {
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    // Add secrets as environment variables, on top of the inherited environment
+    for injection in injections {
+        match &injection.file {
+            Some(content) => cmd.env(
+                &injection.name,
+                files.write(&injection.name, &injection.key, content)?,
+            ),
+            None => cmd.env(&injection.name, &injection.value),
+        };
+    }
+
+    Ok(cmd)
+}
+
+enum Outcome {
+    Exited(ExitStatus),
+    /// Stopped because the secrets changed; start again with these
+    Restarted(Vec<Injection>),
+    /// Stopped because the user lost access, for this reason
+    Revoked(String),
+}
+
+/// Runs the command until it exits, passing signals on so it can shut down
+/// cleanly while we stay around to clean up. With a watcher, the command is
+/// stopped (`restart_signal`, then a kill after `grace`) once its secrets change
+/// or the user loses access.
+/// With a redactor, the command's output goes through it.
+async fn supervise(
+    mut cmd: tokio::process::Command,
+    signals: &mut Signals,
+    watch: Option<(&mut Watcher, &[Injection])>,
+    restart_signal: i32,
+    grace: Duration,
+    redactor: Option<Arc<Redactor>>,
+) -> io::Result<Outcome> {
+    let mut child = cmd.spawn()?;
+    let pumps = match redactor {
+        Some(redactor) => redact_output(&mut child, redactor),
+        None => Vec::new(),
+    };
+    let changed = async {
+        match watch {
+            Some((watcher, current)) => watcher.changed(current).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(changed);
+
+    loop {
+        tokio::select! {
+            status = child.wait() => {
+                drain(pumps).await;
+                return Ok(Outcome::Exited(status?));
+            }
+            signal = signals.next() => send_signal(&mut child, signal),
+            change = &mut changed => {
+                send_signal(&mut child, restart_signal);
+                if tokio::time::timeout(grace, child.wait()).await.is_err() {
+                    child.kill().await?;
+                }
+                drain(pumps).await;
+                return Ok(match change {
+                    Change::Secrets(injections) => Outcome::Restarted(injections),
+                    Change::Revoked(reason) => Outcome::Revoked(reason),
+                });
+            }
+        }
+    }
+}
+
+/// Copies the child's piped stdout and stderr to ours through `redactor`.
+fn redact_output(child: &mut Child, redactor: Arc<Redactor>) -> Vec<JoinHandle<io::Result<()>>> {
+    let mut pumps = Vec::new();
+
+    if let Some(stdout) = child.stdout.take() {
+        let redactor = redactor.clone();
+        pumps.push(tokio::spawn(async move {
+            redactor.pipe(stdout, tokio::io::stdout()).await
+        }));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        pumps.push(tokio::spawn(async move {
+            redactor.pipe(stderr, tokio::io::stderr()).await
+        }));
+    }
+
+    pumps
+}
+
+/// Waits for the last of the child's output to be written.
+async fn drain(pumps: Vec<JoinHandle<io::Result<()>>>) {
+    for pump in pumps {
+        let _ = pump.await;
+    }
+}
+
+/// The child's exit code, or 128+N when it was killed by signal N, as shells report it.
+fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
 
-    if !status.success() {
-        if let Some(code) = status.code() {
-            std::process::exit(code);
+/// SIGINT, SIGTERM and SIGHUP sent to us, to be passed on to the child.
+#[cfg(unix)]
+struct Signals {
+    interrupt: tokio::signal::unix::Signal,
+    terminate: tokio::signal::unix::Signal,
+    hangup: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl Signals {
+    fn new() -> io::Result<Self> {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        Ok(Self {
+            interrupt: signal(SignalKind::interrupt())?,
+            terminate: signal(SignalKind::terminate())?,
+            hangup: signal(SignalKind::hangup())?,
+        })
+    }
+
+    async fn next(&mut self) -> i32 {
+        loop {
+            tokio::select! {
+                _ = self.interrupt.recv() => {
+                    // Ctrl-C on our terminal already reached the child; a second
+                    // SIGINT would make many programs skip their graceful shutdown
+                    if !in_foreground() {
+                        return libc::SIGINT;
+                    }
+                }
+                _ = self.terminate.recv() => return libc::SIGTERM,
+                _ = self.hangup.recv() => return libc::SIGHUP,
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn send_signal(child: &mut Child, signal: i32) {
+    if let Some(pid) = child.id() {
+        // SAFETY: kill has no memory-safety requirements
+        unsafe { libc::kill(pid as libc::pid_t, signal) };
+    }
+}
+
+#[cfg(unix)]
+fn signal_number(name: &str) -> Result<i32> {
+    let name = name.to_uppercase();
+    Ok(match name.strip_prefix("SIG").unwrap_or(&name) {
+        "INT" => libc::SIGINT,
+        "TERM" => libc::SIGTERM,
+        "HUP" => libc::SIGHUP,
+        "QUIT" => libc::SIGQUIT,
+        "USR1" => libc::SIGUSR1,
+        "USR2" => libc::SIGUSR2,
+        "KILL" => libc::SIGKILL,
+        other => other
+            .parse()
+            .map_err(|_| anyhow!("Unknown signal: {}", name))?,
+    })
+}
+
+/// Whether we (and so the child, which shares our process group) are the
+/// terminal's foreground job.
+#[cfg(unix)]
+fn in_foreground() -> bool {
+    (0..=2).any(|fd| {
+        // SAFETY: plain queries on file descriptors we don't own or close
+        unsafe { libc::isatty(fd) == 1 && libc::tcgetpgrp(fd) == libc::getpgrp() }
+    })
+}
+
+/// Ctrl-C reaches the child through the console; we only need to survive it.
+#[cfg(not(unix))]
+struct Signals;
+
+#[cfg(not(unix))]
+impl Signals {
+    fn new() -> io::Result<Self> {
+        Ok(Self)
+    }
+
+    async fn next(&mut self) -> i32 {
+        loop {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+/// Without Unix signals the child can only be stopped outright.
+#[cfg(not(unix))]
+fn send_signal(child: &mut Child, _signal: i32) {
+    let _ = child.start_kill();
+}
+
+#[cfg(not(unix))]
+fn signal_number(_name: &str) -> Result<i32> {
+    Ok(0)
+}
+
+/// Notices vault changes for `run --watch`. Reading the vault, hashing and
+/// decrypting all block, so they run off the async threads that forward signals.
+struct Watcher {
+    master_key: Arc<str>,
+    username: Arc<str>,
+    options: Arc<RunOptions>,
+    vault: Option<String>,
+    head: Option<String>,
+}
+
+/// What a watcher noticed
+enum Change {
+    /// The secrets the child would get now
+    Secrets(Vec<Injection>),
+    /// The user was removed or suspended, so the child must not keep its secrets
+    Revoked(String),
+}
+
+impl Watcher {
+    fn new(master_key: &str, username: &str, options: &RunOptions) -> Self {
+        let (vault, head) = Self::snapshot();
+        Self {
+            master_key: master_key.into(),
+            username: username.into(),
+            options: Arc::new(options.clone()),
+            vault,
+            head,
+        }
+    }
+
+    fn snapshot() -> (Option<String>, Option<String>) {
+        let vault = ConfigManager::load_raw().ok().flatten();
+        let head = std::env::current_dir()
+            .ok()
+            .and_then(|dir| GitManager::head_commit(&dir));
+        (vault, head)
+    }
+
+    /// Waits until the vault or HEAD moves and the secrets the child would
+    /// get are no longer `current`, or the user loses access.
+    async fn changed(&mut self, current: &[Injection]) -> Change {
+        loop {
+            tokio::time::sleep(WATCH_INTERVAL).await;
+
+            let Ok((vault, head)) = tokio::task::spawn_blocking(Self::snapshot).await else {
+                continue;
+            };
+            if vault == self.vault && head == self.head {
+                continue;
+            }
+            self.vault = vault;
+            self.head = head;
+
+            let (master_key, username, options) = (
+                self.master_key.clone(),
+                self.username.clone(),
+                self.options.clone(),
+            );
+            let reloaded =
+                tokio::task::spawn_blocking(move || Self::reload(&master_key, &username, &options))
+                    .await
+                    .unwrap_or_else(|e| Err(anyhow!("{}", e)));
+
+            match reloaded {
+                Ok(Change::Secrets(injections)) if injections == current => {}
+                Ok(change) => return change,
+                // Mid-write or mid-merge; the next change will be picked up
+                Err(e) => UI::warning(&format!("Could not reload secrets: {}", e)),
+            }
+        }
+    }
+
+    fn reload(master_key: &str, username: &str, options: &RunOptions) -> Result<Change> {
+        let (_, private_config) = ConfigManager::load_full_config(master_key)?;
+        let Some(user) = private_config.users.get(username) else {
+            return Ok(Change::Revoked(format!(
+                "User {} no longer exists",
+                username
+            )));
+        };
+        if let Err(e) = user.ensure_active() {
+            return Ok(Change::Revoked(e.to_string()));
+        }
+
+        let mut injections = resolve(&private_config, master_key, username, options)?;
+        keep_inherited(&mut injections, options);
+        Ok(Change::Secrets(injections))
+    }
+}
+
+/// Whether a `--only`/`--exclude` list names this secret, by vault key or,
+/// for files, by variable name.
+fn listed(list: &[String], secret: &SecretValue) -> bool {
+    list.iter()
+        .any(|k| *k == secret.key || (secret.is_file && *k == env_name(&secret.key)))
+}
+
+/// Environment variable name for vault key `key`.
+fn variable_name(key: &str, options: &RunOptions) -> String {
+    let stripped = options
+        .strip_prefix
+        .as_deref()
+        .and_then(|prefix| key.strip_prefix(prefix))
+        .filter(|rest| !rest.is_empty())
+        .unwrap_or(key);
+
+    format!("{}{}", options.prefix.as_deref().unwrap_or(""), stripped)
+}
+
+fn print_plan(injections: &[Injection], options: &RunOptions) {
+    if injections.is_empty() {
+        UI::warning("No accessible secrets found");
+        return;
+    }
+
+    UI::header("Variables that would be set");
+    for injection in injections {
+        let source = if injection.file.is_some() {
+            format!("path to file {}", injection.key)
+        } else if injection.name == injection.key {
+            "from vault".to_string()
         } else {
-            std::process::exit(1);
+            format!("from {}", injection.key)
+        };
+        let note = match (injection.existing, options.no_override) {
+            (true, true) => " (already set, kept)",
+            (true, false) => " (replaces inherited value)",
+            _ => "",
+        };
+        UI::table_row(&injection.name, &format!("{}{}", source, note));
+    }
+
+    if options.clean_env {
+        UI::info("No other variables would be passed (--clean-env)");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Permissions, Secret};
+    use uuid::Uuid;
+
+    const MASTER_KEY: &str = "master-key-for-tests";
+
+    /// A vault holding these string secrets, plus file secret `tls/cert.pem`.
+    /// `DEPLOY_TOKEN` is restricted to carol.
+    fn vault(keys: &[&str]) -> PrivateConfig {
+        let mut values: Vec<SecretValue> = keys
+            .iter()
+            .map(|key| SecretValue {
+                key: key.to_string(),
+                value: format!("{}-value", key),
+                is_file: false,
+                file_content: None,
+            })
+            .collect();
+        values.push(SecretValue {
+            key: "tls/cert.pem".to_string(),
+            value: String::new(),
+            is_file: true,
+            file_content: Some(b"certificate".to_vec()),
+        });
+
+        let secrets = values
+            .iter()
+            .map(|value| {
+                let users = if value.key == "DEPLOY_TOKEN" {
+                    vec!["carol".to_string()]
+                } else {
+                    Vec::new()
+                };
+                let secret = Secret {
+                    id: Uuid::new_v4(),
+                    key: value.key.clone(),
+                    created_at: "2026-01-01T00:00:00Z".to_string(),
+                    updated_at: "2026-01-01T00:00:00Z".to_string(),
+                    created_by: "admin".to_string(),
+                    permissions: Permissions {
+                        users,
+                        groups: Vec::new(),
+                    },
+                    is_file: value.is_file,
+                    file_path: None,
+                    tags: Vec::new(),
+                };
+                (value.key.clone(), secret)
+            })
+            .collect();
+
+        let data = serde_json::to_vec(&EncryptedSecrets { secrets: values }).unwrap();
+        PrivateConfig {
+            users: HashMap::new(),
+            groups: HashMap::new(),
+            secrets,
+            encrypted_secrets: CryptoManager::encrypt_data_with_salt(&data, MASTER_KEY).unwrap(),
         }
     }
 
-    Ok(())
+    fn names(injections: &[Injection]) -> Vec<&str> {
+        injections.iter().map(|i| i.name.as_str()).collect()
+    }
+
+    #[test]
+    fn vault_values_replace_inherited_variables() {
+        // PATH is set in every test environment
+        let private_config = vault(&["PATH", "API_KEY"]);
+        let options = RunOptions::default();
+
+        let mut injections = resolve(&private_config, MASTER_KEY, "bob", &options).unwrap();
+        assert_eq!(names(&injections), ["API_KEY", "PATH", "TLS_CERT_PEM"]);
+        assert!(
+            injections
+                .iter()
+                .find(|i| i.name == "PATH")
+                .unwrap()
+                .existing
+        );
+        assert_eq!(keep_inherited(&mut injections, &options), 0);
+
+        let cmd = build_command(
+            &["env".to_string()],
+            &injections,
+            &options,
+            &mut SecretFiles::default(),
+        )
+        .unwrap();
+        let path = cmd
+            .get_envs()
+            .find(|(name, _)| *name == "PATH")
+            .and_then(|(_, value)| value);
+        assert_eq!(path, Some("PATH-value".as_ref()));
+    }
+
+    #[test]
+    fn no_override_keeps_inherited_variables() {
+        let private_config = vault(&["PATH", "API_KEY"]);
+        let options = RunOptions {
+            no_override: true,
+            ..Default::default()
+        };
+
+        let mut injections = resolve(&private_config, MASTER_KEY, "bob", &options).unwrap();
+        assert_eq!(keep_inherited(&mut injections, &options), 1);
+        assert_eq!(names(&injections), ["API_KEY", "TLS_CERT_PEM"]);
+    }
+
+    #[test]
+    fn clean_env_has_nothing_to_keep() {
+        let private_config = vault(&["PATH"]);
+        let options = RunOptions {
+            no_override: true,
+            clean_env: true,
+            ..Default::default()
+        };
+
+        let mut injections = resolve(&private_config, MASTER_KEY, "bob", &options).unwrap();
+        assert_eq!(keep_inherited(&mut injections, &options), 0);
+        assert_eq!(names(&injections), ["PATH", "TLS_CERT_PEM"]);
+    }
+
+    #[test]
+    fn only_and_exclude_select_by_key_or_variable_name() {
+        let private_config = vault(&["API_KEY", "DB_URL", "DEPLOY_TOKEN"]);
+
+        let only = RunOptions {
+            only: vec!["DB_URL".to_string(), "TLS_CERT_PEM".to_string()],
+            ..Default::default()
+        };
+        let injections = resolve(&private_config, MASTER_KEY, "bob", &only).unwrap();
+        assert_eq!(names(&injections), ["DB_URL", "TLS_CERT_PEM"]);
+
+        let exclude = RunOptions {
+            exclude: vec!["API_KEY".to_string(), "tls/cert.pem".to_string()],
+            ..Default::default()
+        };
+        let injections = resolve(&private_config, MASTER_KEY, "bob", &exclude).unwrap();
+        assert_eq!(names(&injections), ["DB_URL"]);
+
+        // --only can't reach secrets the user has no access to
+        let restricted = RunOptions {
+            only: vec!["DEPLOY_TOKEN".to_string()],
+            ..Default::default()
+        };
+        assert!(
+            resolve(&private_config, MASTER_KEY, "bob", &restricted)
+                .unwrap()
+                .is_empty()
+        );
+        let injections = resolve(&private_config, MASTER_KEY, "carol", &restricted).unwrap();
+        assert_eq!(names(&injections), ["DEPLOY_TOKEN"]);
+    }
+
+    #[test]
+    fn env_selects_matching_keys() {
+        let private_config = vault(&["DB_URL_PROD", "DB_URL_STAGING"]);
+        let options = RunOptions {
+            env: Some("prod".to_string()),
+            ..Default::default()
+        };
+
+        let injections = resolve(&private_config, MASTER_KEY, "bob", &options).unwrap();
+        assert_eq!(names(&injections), ["DB_URL_PROD"]);
+    }
+
+    #[test]
+    fn prefixes_rename_variables() {
+        let private_config = vault(&["APP_DB_URL", "APP_", "OTHER"]);
+        let options = RunOptions {
+            strip_prefix: Some("APP_".to_string()),
+            prefix: Some("MY_".to_string()),
+            file_suffix: Some("_FILE".to_string()),
+            ..Default::default()
+        };
+
+        let injections = resolve(&private_config, MASTER_KEY, "bob", &options).unwrap();
+        let renamed: Vec<(&str, &str)> = injections
+            .iter()
+            .map(|i| (i.key.as_str(), i.name.as_str()))
+            .collect();
+        // A key that is only the prefix keeps its name
+        assert_eq!(
+            renamed,
+            [
+                ("APP_", "MY_APP_"),
+                ("APP_DB_URL", "MY_DB_URL"),
+                ("OTHER", "MY_OTHER"),
+                ("tls/cert.pem", "MY_TLS_CERT_PEM_FILE"),
+            ]
+        );
+    }
+
+    #[test]
+    fn colliding_names_are_rejected() {
+        let private_config = vault(&["APP_DB_URL", "DB_URL"]);
+        let options = RunOptions {
+            strip_prefix: Some("APP_".to_string()),
+            ..Default::default()
+        };
+
+        let err = resolve(&private_config, MASTER_KEY, "bob", &options)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("would both set DB_URL"));
+    }
 }
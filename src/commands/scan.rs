@@ -0,0 +1,154 @@
+use crate::config::ConfigManager;
+use crate::credential_manager::CredentialManager;
+use crate::crypto::CryptoManager;
+use crate::git::GitManager;
+use crate::secret_scan::{Baseline, Finding, SecretScanner, describe, sarif};
+use crate::types::EncryptedSecrets;
+use crate::ui::UI;
+use anyhow::{Result, anyhow};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+/// Files bigger than this are build artifacts or data dumps, not config
+const MAX_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// Environment variable naming the vault directory when scanning another repository
+pub const VAULT_ENV: &str = "SMOLCASE_VAULT";
+
+pub async fn execute(
+    path: Option<PathBuf>,
+    staged: bool,
+    history: bool,
+    format: String,
+    baseline: Option<PathBuf>,
+    update_baseline: bool,
+    vault: Option<PathBuf>,
+) -> Result<()> {
+    if !matches!(format.as_str(), "text" | "json" | "sarif") {
+        return Err(anyhow!("Unsupported format: {}", format));
+    }
+
+    // Paths given on the command line stay relative to where scan was run,
+    // even when the vault lives in another directory
+    let current_dir = std::env::current_dir()?;
+    let root = path.map_or_else(|| current_dir.clone(), |p| current_dir.join(p));
+    let baseline = baseline.map(|p| current_dir.join(p));
+
+    if let Some(vault_dir) = vault.or_else(|| std::env::var_os(VAULT_ENV).map(PathBuf::from)) {
+        std::env::set_current_dir(&vault_dir)
+            .map_err(|e| anyhow!("Cannot open vault {}: {}", vault_dir.display(), e))?;
+    }
+    if !ConfigManager::is_smolcase_project() {
+        return Err(anyhow!(
+            "Not a smolcase project. Run scan from the vault, or point --vault or {} at it.",
+            VAULT_ENV
+        ));
+    }
+
+    let cached_creds = CredentialManager::load_credentials()?;
+    let master_key = CredentialManager::get_master_key(&cached_creds)?;
+    let (_, private_config) = ConfigManager::load_full_config(&master_key)?;
+
+    let values = if private_config.encrypted_secrets.is_empty() {
+        HashMap::new()
+    } else {
+        let decrypted_data =
+            CryptoManager::decrypt_data_with_salt(&private_config.encrypted_secrets, &master_key)?;
+        let secrets: EncryptedSecrets = serde_json::from_slice(&decrypted_data)?;
+        secrets
+            .secrets
+            .into_iter()
+            .map(|s| (s.key.clone(), s))
+            .collect()
+    };
+    let scanner = SecretScanner::new(&values, SecretScanner::fingerprint_key(&master_key)?);
+    let mut findings = Vec::new();
+    let mut scanned = 0;
+
+    if staged {
+        if !GitManager::is_inside_git_repo(&current_dir) {
+            return Err(anyhow!("Not a Git repository"));
+        }
+        for (name, content) in GitManager::staged_files(&current_dir)? {
+            findings.extend(scanner.scan(&name, &content));
+            scanned += 1;
+        }
+    } else {
+        for (name, file) in GitManager::working_files(&root)? {
+            if fs::metadata(&file).is_ok_and(|m| m.len() > MAX_FILE_SIZE) {
+                continue;
+            }
+            if let Ok(content) = fs::read(&file) {
+                findings.extend(scanner.scan(&name, &content));
+                scanned += 1;
+            }
+        }
+    }
+
+    if history {
+        let mut seen: HashSet<String> = findings.iter().map(|f| f.fingerprint.clone()).collect();
+        GitManager::added_lines(&root, |commit, file, line_number, line| {
+            for mut finding in scanner.scan_line(file, line_number, line) {
+                // Report each leak once, at the commit that introduced it
+                if seen.insert(finding.fingerprint.clone()) {
+                    finding.commit = Some(commit.to_string());
+                    findings.push(finding);
+                }
+            }
+        })?;
+    }
+
+    if update_baseline {
+        let baseline_path =
+            baseline.ok_or_else(|| anyhow!("--update-baseline needs --baseline FILE"))?;
+        Baseline::from_findings(&findings).save(&baseline_path)?;
+        UI::success(&format!(
+            "Wrote {} accepted finding(s) to {}",
+            findings.len(),
+            baseline_path.display()
+        ));
+        return Ok(());
+    }
+
+    let accepted = match &baseline {
+        Some(baseline_path) => Baseline::load(baseline_path)?,
+        None => Baseline::default(),
+    };
+    let total = findings.len();
+    findings.retain(|f| !accepted.contains(f));
+
+    match format.as_str() {
+        "json" => println!("{}", serde_json::to_string_pretty(&findings)?),
+        "sarif" => println!("{}", serde_json::to_string_pretty(&sarif(&findings))?),
+        _ => print_text(&findings, scanned, total - findings.len()),
+    }
+
+    if findings.is_empty() {
+        return Ok(());
+    }
+
+    Err(anyhow!(
+        "Found {} possible secret leak(s){}. Remove the values, or use 'smolcase run' to inject them at runtime.",
+        findings.len(),
+        if staged { " in staged files" } else { "" }
+    ))
+}
+
+fn print_text(findings: &[Finding], scanned: usize, accepted: usize) {
+    for finding in findings {
+        UI::error(&format!(
+            "{}:{}: {}",
+            finding.path,
+            finding.line,
+            describe(finding)
+        ));
+    }
+
+    if findings.is_empty() {
+        UI::success(&format!("No secret values found in {} files", scanned));
+    }
+    if accepted > 0 {
+        UI::info(&format!("{} finding(s) accepted by the baseline", accepted));
+    }
+}
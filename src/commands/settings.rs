@@ -0,0 +1,54 @@
+use crate::SettingsAction;
+use crate::audit_log::AuditLog;
+use crate::config::ConfigManager;
+use crate::credential_manager::CredentialManager;
+use crate::crypto::CryptoManager;
+use crate::ui::UI;
+use anyhow::{Result, anyhow};
+
+pub async fn execute(action: Option<SettingsAction>) -> Result<()> {
+    let public_config = ConfigManager::load_public_config()?;
+
+    match action.unwrap_or(SettingsAction::Show) {
+        SettingsAction::Show => {
+            UI::header("Project Settings");
+            for (key, value) in public_config.settings.entries() {
+                UI::table_row(key, &value);
+            }
+        }
+
+        SettingsAction::Set { key, value } => {
+            let cached_creds = CredentialManager::load_credentials()?;
+
+            if !cached_creds.is_admin {
+                return Err(anyhow!(
+                    "Only admins can change project settings. Use 'smolcase configure' to set up admin credentials."
+                ));
+            }
+
+            let admin_password = CredentialManager::get_admin_password(&cached_creds)?;
+            if !CryptoManager::verify_password(&admin_password, &public_config.admin_key_hash)? {
+                return Err(anyhow!("Invalid admin password"));
+            }
+
+            let master_key = CredentialManager::get_master_key(&cached_creds)?;
+            let (mut public_config, private_config) = ConfigManager::load_full_config(&master_key)?;
+
+            public_config.settings.set(&key, &value)?;
+            ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+
+            let actor = cached_creds.username.as_deref().unwrap_or("admin");
+            AuditLog::record_or_warn(
+                &master_key,
+                actor,
+                "settings.set",
+                &[],
+                Some(format!("{} = {}", key, value)),
+            );
+
+            UI::success(&format!("Set {} = {}", key, value));
+        }
+    }
+
+    Ok(())
+}
@@ -1,36 +1,38 @@
-use crate::config::ConfigManager;
-use crate::credential_manager::CredentialManager;
+use crate::config::{CONFIG_FILE, ConfigManager};
 use crate::crypto::CryptoManager;
 use crate::git::GitManager;
+use crate::storage::STORAGE_FILE;
 use crate::ui::UI;
 use anyhow::{Result, anyhow};
-use std::path::Path;
-
-pub async fn execute(repo: Option<String>) -> Result<()> {
-    let repo_path = if let Some(repo) = repo {
-        if repo.starts_with("http") {
-            let repo_name = repo
-                .split('/')
-                .last()
-                .unwrap_or("smolcase-repo")
-                .replace(".git", "");
-            let target_path = Path::new(&repo_name);
-
-            if target_path.exists() {
-                return Err(anyhow!("Directory '{}' already exists", repo_name));
-            }
-
-            UI::info(&format!("Cloning repository: {}", repo));
-            GitManager::clone_repo(&repo, target_path)?;
-
-            std::env::set_current_dir(target_path)?;
-            repo_name
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub async fn execute(
+    repo: Option<String>,
+    dir: Option<PathBuf>,
+    branch: Option<String>,
+) -> Result<()> {
+    if let Some(repo) = repo {
+        let local = Path::new(&repo);
+        let in_place = !GitManager::is_remote_url(&repo)
+            && (local.join(CONFIG_FILE).exists() || local.join(STORAGE_FILE).exists())
+            && dir.is_none()
+            && branch.is_none();
+
+        if in_place {
+            std::env::set_current_dir(local)?;
+        } else if GitManager::is_remote_url(&repo) || GitManager::is_git_repo(local) {
+            let target = dir.unwrap_or_else(|| PathBuf::from(repo_name(&repo)));
+            clone_vault(&repo, &target, branch.as_deref())?;
         } else {
-            repo
+            return Err(anyhow!(
+                "'{}' is neither a Git URL nor a directory containing a smolcase vault",
+                repo
+            ));
         }
-    } else {
-        ".".to_string()
-    };
+    } else if dir.is_some() || branch.is_some() {
+        return Err(anyhow!("--dir and --branch need --repo"));
+    }
 
     if !ConfigManager::is_smolcase_project() {
         return Err(anyhow!("Not a smolcase project"));
@@ -52,6 +54,7 @@ pub async fn execute(repo: Option<String>) -> Result<()> {
 
     if let Some(user) = private_config.users.get(&username) {
         if CryptoManager::verify_password(&password, &user.password_hash)? {
+            user.ensure_active()?;
             UI::success(&format!("Access granted for user: {}", username));
             UI::info("You can now use 'smolcase get' to retrieve secrets");
             UI::info("Run 'smolcase configure' to cache credentials for easier access");
@@ -64,3 +67,79 @@ pub async fn execute(repo: Option<String>) -> Result<()> {
 
     Ok(())
 }
+
+/// Clones `repo` into `target` and makes it the current directory.
+fn clone_vault(repo: &str, target: &Path, branch: Option<&str>) -> Result<()> {
+    if target.exists() && target.read_dir()?.next().is_some() {
+        return Err(anyhow!(
+            "Directory '{}' already exists and is not empty",
+            target.display()
+        ));
+    }
+
+    UI::info(&format!("Cloning repository: {}", repo));
+    GitManager::clone_repo(repo, target, branch)?;
+
+    // Check before asking for any credentials. The clone may only hold
+    // .smolcase-storage.yml, pointing at a vault kept elsewhere.
+    let previous_dir = std::env::current_dir()?;
+    let target = target.canonicalize()?;
+    std::env::set_current_dir(&target)?;
+    if ConfigManager::load_raw()
+        .ok()
+        .flatten()
+        .and_then(|c| ConfigManager::parse_config(&c).ok())
+        .is_none()
+    {
+        std::env::set_current_dir(previous_dir)?;
+        fs::remove_dir_all(&target)?;
+        return Err(anyhow!(
+            "{}{} has no smolcase vault ({} is missing or invalid, and {} points to no vault)",
+            repo,
+            branch
+                .map(|b| format!(" (branch {})", b))
+                .unwrap_or_default(),
+            CONFIG_FILE,
+            STORAGE_FILE
+        ));
+    }
+
+    UI::success(&format!("Cloned into {}", target.display()));
+    Ok(())
+}
+
+/// `git@host:org/secrets.git`, `file:///srv/secrets.git/` and `../secrets` all give "secrets".
+fn repo_name(repo: &str) -> String {
+    let name = repo
+        .trim_end_matches('/')
+        .rsplit(['/', ':', '\\'])
+        .next()
+        .unwrap_or_default();
+    let name = name.strip_suffix(".git").unwrap_or(name);
+
+    if name.is_empty() {
+        "smolcase-repo".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repo_names() {
+        assert_eq!(repo_name("git@github.com:org/secrets.git"), "secrets");
+        assert_eq!(repo_name("file:///srv/secrets.git/"), "secrets");
+        assert_eq!(repo_name("https://host/org/team-vault"), "team-vault");
+        assert_eq!(repo_name("../secrets"), "secrets");
+        assert_eq!(repo_name("C:\\vaults\\secrets"), "secrets");
+    }
+
+    #[test]
+    fn falls_back_when_nothing_is_left() {
+        assert_eq!(repo_name("/"), "smolcase-repo");
+        assert_eq!(repo_name(".git"), "smolcase-repo");
+    }
+}
@@ -1,10 +1,15 @@
+use crate::access::AccessTracker;
 use crate::config::ConfigManager;
 use crate::credential_manager::CredentialManager;
 use crate::git::GitManager;
+use crate::signing::VaultSigner;
+use crate::storage::Storage;
 use crate::ui::UI;
 use anyhow::Result;
 use colored::*;
 
+const DORMANT_DAYS: i64 = 90;
+
 pub async fn execute() -> Result<()> {
     if !ConfigManager::is_smolcase_project() {
         return Err(anyhow::anyhow!("Not a smolcase project"));
@@ -18,6 +23,17 @@ pub async fn execute() -> Result<()> {
 
     UI::table_row("Version", &public_config.version);
     UI::table_row("Created", &public_config.created_at);
+    UI::table_row("Revision", &public_config.revision.to_string());
+    UI::table_row(
+        "Vault Signature",
+        if VaultSigner::is_signed(&public_config) {
+            "Signed"
+        } else if cached_creds.vault_public_key.is_some() {
+            "Missing (the signing key was removed)"
+        } else {
+            "Not signed (run 'smolcase admin sign')"
+        },
+    );
 
     // Show credential status
     if cached_creds.is_admin {
@@ -90,6 +106,56 @@ pub async fn execute() -> Result<()> {
                 UI::table_row("Admins", &admin_count.to_string());
                 UI::table_row("Users", &user_count.to_string());
                 UI::table_row("Groups", &private_config.groups.len().to_string());
+
+                let suspended_count = private_config.users.values().filter(|u| u.disabled).count();
+                if suspended_count > 0 {
+                    UI::table_row("Suspended", &suspended_count.to_string());
+                }
+
+                let mut expired: Vec<_> = private_config
+                    .users
+                    .values()
+                    .filter(|u| u.is_expired())
+                    .map(|u| u.username.as_str())
+                    .collect();
+                if !expired.is_empty() {
+                    expired.sort();
+                    println!();
+                    UI::warning(&format!("Expired accounts: {}", expired.join(", ")));
+                    UI::info("Run 'smolcase user remove <NAME>' to clean them up");
+                }
+            }
+
+            if let Ok(access_log) = AccessTracker::load(&master_key) {
+                let mut dormant_users: Vec<_> = private_config
+                    .users
+                    .keys()
+                    .filter(|name| {
+                        AccessTracker::is_dormant(access_log.users.get(*name), DORMANT_DAYS)
+                    })
+                    .map(|name| name.as_str())
+                    .collect();
+                let mut unused_secrets: Vec<_> = private_config
+                    .secrets
+                    .keys()
+                    .filter(|key| {
+                        AccessTracker::is_dormant(access_log.secrets.get(*key), DORMANT_DAYS)
+                    })
+                    .map(|key| key.as_str())
+                    .collect();
+
+                if !dormant_users.is_empty() || !unused_secrets.is_empty() {
+                    println!();
+                    UI::header(&format!("Unused in the last {} days", DORMANT_DAYS));
+                    dormant_users.sort();
+                    unused_secrets.sort();
+                    if !dormant_users.is_empty() {
+                        UI::table_row("Users", &dormant_users.join(", "));
+                    }
+                    if !unused_secrets.is_empty() {
+                        UI::table_row("Secrets", &unused_secrets.join(", "));
+                    }
+                }
             }
         } else {
             UI::warning("Could not decrypt project data");
@@ -104,6 +170,9 @@ pub async fn execute() -> Result<()> {
         UI::table_row("Secrets", "encrypted");
     }
 
+    if let Ok(storage) = Storage::open() {
+        UI::table_row("Storage", &storage.describe());
+    }
     UI::table_row(
         "Git Repository",
         if GitManager::is_git_repo(&current_dir) {
@@ -113,7 +182,7 @@ pub async fn execute() -> Result<()> {
         },
     );
 
-    if !cached_creds.master_key.is_some() || !cached_creds.user_password.is_some() {
+    if cached_creds.master_key.is_none() || cached_creds.user_password.is_none() {
         println!();
         UI::info("💡 Run 'smolcase configure' to cache credentials and avoid password prompts");
     }
@@ -0,0 +1,121 @@
+use crate::access::ACCESS_FILE;
+use crate::audit_log::{AUDIT_FILE, AuditLog};
+use crate::config::{CONFIG_FILE, ConfigManager};
+use crate::credential_manager::CredentialManager;
+use crate::crypto::CryptoManager;
+use crate::storage::{STORAGE_FILE, Storage, StorageConfig};
+use crate::ui::UI;
+use crate::{StorageAction, StorageBackend};
+use anyhow::{Result, anyhow};
+
+pub async fn execute(action: Option<StorageAction>) -> Result<()> {
+    if !ConfigManager::is_smolcase_project() {
+        return Err(anyhow!("Not a smolcase project"));
+    }
+
+    match action.unwrap_or(StorageAction::Show) {
+        StorageAction::Show => {
+            let storage = Storage::open()?;
+            UI::table_row("Storage", &storage.describe());
+            UI::table_row(
+                "Vault",
+                if storage.load(CONFIG_FILE)?.is_some() {
+                    "present"
+                } else {
+                    "missing"
+                },
+            );
+        }
+
+        StorageAction::Use { backend } => {
+            let public_config = ConfigManager::load_public_config()?;
+            let cached_creds = CredentialManager::load_credentials()?;
+
+            if !cached_creds.is_admin {
+                return Err(anyhow!(
+                    "Only admins can move the vault. Use 'smolcase configure' to set up admin credentials."
+                ));
+            }
+
+            let admin_password = CredentialManager::get_admin_password(&cached_creds)?;
+            if !CryptoManager::verify_password(&admin_password, &public_config.admin_key_hash)? {
+                return Err(anyhow!("Invalid admin password"));
+            }
+
+            let master_key = CredentialManager::get_master_key(&cached_creds)?;
+            // Only move a vault that loads and verifies
+            ConfigManager::load_full_config(&master_key)?;
+
+            let config = match backend {
+                StorageBackend::File => StorageConfig::File,
+                StorageBackend::Directory { path } => StorageConfig::Directory {
+                    path: std::path::absolute(&path)?,
+                },
+                StorageBackend::S3 {
+                    endpoint,
+                    bucket,
+                    prefix,
+                    region,
+                } => StorageConfig::S3 {
+                    endpoint,
+                    bucket,
+                    prefix,
+                    region,
+                },
+            };
+
+            let source = Storage::open()?;
+            let target = Storage::from_config(&config)?;
+            if source.describe() == target.describe() {
+                UI::info(&format!(
+                    "The vault is already stored in {}",
+                    target.describe()
+                ));
+                return Ok(());
+            }
+
+            if target.load(CONFIG_FILE)?.is_some() {
+                return Err(anyhow!(
+                    "{} already holds a vault. Point {} at it by hand if that is the one to use.",
+                    target.describe(),
+                    STORAGE_FILE
+                ));
+            }
+
+            {
+                let _lock = target.lock()?;
+                for file in [ACCESS_FILE, AUDIT_FILE, CONFIG_FILE] {
+                    if let Some(content) = source.load(file)? {
+                        target.save(file, &content)?;
+                    }
+                }
+            }
+            Storage::save_config(&config)?;
+
+            let actor = cached_creds.username.as_deref().unwrap_or("admin");
+            AuditLog::record_or_warn(
+                &master_key,
+                actor,
+                "storage.use",
+                &[],
+                Some(target.describe()),
+            );
+
+            UI::success(&format!("Vault copied to {}", target.describe()));
+            if target.uses_git() {
+                UI::info(&format!(
+                    "Commit the vault files and the removal of {}",
+                    STORAGE_FILE
+                ));
+            } else {
+                UI::info(&format!(
+                    "Share {} with your team; the copy in {} is no longer used",
+                    STORAGE_FILE,
+                    source.describe()
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
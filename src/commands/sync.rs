@@ -1,9 +1,16 @@
-use crate::config::ConfigManager;
+use crate::access::{ACCESS_FILE, AccessTracker};
+use crate::audit_log::{AUDIT_FILE, AuditLog};
+use crate::config::{CONFIG_FILE, ConfigManager};
 use crate::credential_manager::CredentialManager;
 use crate::crypto::CryptoManager;
-use crate::git::GitManager;
+use crate::git::{CommitAuthor, GitManager, PullOutcome};
+use crate::storage::Storage;
+use crate::types::ProjectSettings;
 use crate::ui::UI;
+use crate::vault_diff::VaultDiff;
+use crate::vault_merge::VaultMerge;
 use anyhow::{Result, anyhow};
+use std::path::Path;
 
 pub async fn execute() -> Result<()> {
     if !ConfigManager::is_smolcase_project() {
@@ -25,8 +32,21 @@ pub async fn execute() -> Result<()> {
         return Err(anyhow!("Invalid admin credentials"));
     }
 
+    let master_key = CredentialManager::get_master_key(&cached_creds)?;
+    // Merging needs a working master key, so check it before touching Git
+    ConfigManager::load_full_config(&master_key)?;
+
     let current_dir = std::env::current_dir()?;
 
+    let storage = Storage::open()?;
+    if !storage.uses_git() {
+        UI::info(&format!(
+            "The vault is stored in {}; changes are shared as soon as they are saved.",
+            storage.describe()
+        ));
+        return Ok(());
+    }
+
     if !GitManager::is_git_repo(&current_dir) {
         UI::warning("Not a Git repository. Initialize with 'git init' first.");
         return Ok(());
@@ -34,10 +54,147 @@ pub async fn execute() -> Result<()> {
 
     UI::info("Syncing with Git repository...");
 
-    GitManager::add_and_commit(&current_dir, "Update smolcase configuration")?;
+    if commit_vault_changes(&current_dir, &master_key, cached_creds.username.as_deref())? {
+        UI::info("Committed local vault changes");
+    }
+
+    let Some(remote) = GitManager::default_remote(&current_dir)? else {
+        UI::warning("No Git remote configured. Add one with 'git remote add origin <URL>'.");
+        return Ok(());
+    };
+
+    pull_and_push(&current_dir, &remote, &master_key)?;
+
+    UI::success(&format!("Synced with '{}'", remote));
+
+    Ok(())
+}
+
+/// Commits pending vault changes as `actor` (or the git config user) with a
+/// message summarizing them. Returns false when there was nothing to commit,
+/// or the vault isn't stored in Git.
+pub fn commit_vault_changes(path: &Path, master_key: &str, actor: Option<&str>) -> Result<bool> {
+    if !Storage::open()?.uses_git() || !GitManager::has_changes(path)? {
+        return Ok(false);
+    }
+
+    let old = GitManager::file_at_revision(path, "HEAD", CONFIG_FILE)
+        .ok()
+        .flatten();
+    // An undecryptable HEAD (e.g. after a master key rotation) just means a less specific message
+    let old = VaultDiff::snapshot_of(old.as_deref(), master_key)
+        .ok()
+        .flatten();
+    let current = ConfigManager::load_raw()?.ok_or_else(|| anyhow!("Vault config is missing"))?;
+    let new = VaultDiff::snapshot(&current, master_key)?;
+
+    let changes = VaultDiff::compare(old.as_ref(), Some(&new), false);
+    let message = if changes.is_empty() {
+        "smolcase: update access and audit logs".to_string()
+    } else {
+        VaultDiff::commit_message(&changes)
+    };
+
+    let author = actor.map(|name| CommitAuthor {
+        name: name.to_string(),
+        email: new.private.users.get(name).and_then(|u| u.email.clone()),
+    });
+    GitManager::add_and_commit(path, &message, author.as_ref())?;
+
+    Ok(true)
+}
+
+/// Fetches `remote`, merges its vault changes (decrypting where needed) and pushes.
+pub fn pull_and_push(path: &Path, remote: &str, master_key: &str) -> Result<()> {
+    UI::info(&format!("Fetching from '{}'...", remote));
+
+    let mut notes = Vec::new();
+    let outcome = GitManager::pull(path, remote, |file, base, ours, theirs| match file {
+        CONFIG_FILE => {
+            let (merged, merge_notes) = VaultMerge::merge(base, ours, theirs, master_key)?;
+            notes.extend(merge_notes);
+            Ok(merged)
+        }
+        ACCESS_FILE => AccessTracker::merge(ours, theirs, master_key),
+        AUDIT_FILE => AuditLog::merge(base, ours, theirs, master_key),
+        _ => Err(anyhow!(
+            "Merge conflict in {}. Resolve it with git, then run 'smolcase sync' again.",
+            file
+        )),
+    })?;
 
-    UI::success("Synced successfully!");
-    UI::info("Don't forget to push changes to remote repository");
+    match outcome {
+        PullOutcome::UpToDate => UI::info("Already up to date with remote"),
+        PullOutcome::RemoteEmpty => UI::info("Remote branch does not exist yet"),
+        PullOutcome::FastForward => UI::info("Fast-forwarded to remote changes"),
+        PullOutcome::Merged(resolved) => {
+            if resolved.is_empty() {
+                UI::info("Merged remote changes");
+            } else {
+                UI::info(&format!(
+                    "Merged remote changes (decrypted to resolve {})",
+                    resolved.join(", ")
+                ));
+            }
+        }
+    }
+    for note in &notes {
+        UI::warning(note);
+    }
+
+    GitManager::push(path, remote)?;
+
+    Ok(())
+}
+
+/// Runs after a mutating command: commits the vault when the project has
+/// `auto_commit` enabled, and pulls/pushes too with `auto_push`. Failures only
+/// warn, since the change itself is already saved.
+pub fn auto_commit(
+    settings: &ProjectSettings,
+    master_key: &str,
+    actor: Option<&str>,
+    no_commit: bool,
+) {
+    if no_commit || !settings.auto_commit {
+        return;
+    }
+    // Other backends share changes on save, there is nothing to commit
+    if !Storage::open().is_ok_and(|storage| storage.uses_git()) {
+        return;
+    }
+
+    if let Err(e) = commit_and_push(settings, master_key, actor) {
+        UI::warning(&format!(
+            "Auto-commit failed: {}. Run 'smolcase sync' to retry.",
+            e
+        ));
+    }
+}
+
+fn commit_and_push(
+    settings: &ProjectSettings,
+    master_key: &str,
+    actor: Option<&str>,
+) -> Result<()> {
+    let current_dir = std::env::current_dir()?;
+    if !GitManager::is_git_repo(&current_dir) {
+        return Err(anyhow!("not a Git repository"));
+    }
+
+    if commit_vault_changes(&current_dir, master_key, actor)? {
+        UI::info("Committed vault change");
+    }
+
+    if settings.auto_push {
+        match GitManager::default_remote(&current_dir)? {
+            Some(remote) => {
+                pull_and_push(&current_dir, &remote, master_key)?;
+                UI::info(&format!("Pushed to '{}'", remote));
+            }
+            None => UI::warning("auto_push is enabled but no Git remote is configured"),
+        }
+    }
 
     Ok(())
 }
@@ -1,3 +1,4 @@
+use crate::RunOptions;
 use crate::commands::{add, export, init, run};
 use crate::ui::UI;
 use anyhow::Result;
@@ -74,6 +75,8 @@ pub async fn execute() -> Result<()> {
         Some("sk-tutorial-1234567890abcdef".to_string()),
         None,
         None,
+        None,
+        false,
     )
     .await?;
 
@@ -115,7 +118,7 @@ pub async fn execute() -> Result<()> {
         "\n{}",
         "Running 'env | grep TUTORIAL' with secrets...".cyan()
     );
-    run::execute(None, vec!["env".to_string()])
+    run::execute(RunOptions::default(), vec!["env".to_string()])
         .await
         .unwrap_or_else(|_| {
             // Command might fail on some systems, that's ok for tutorial
@@ -127,14 +130,14 @@ pub async fn execute() -> Result<()> {
         println!("To collaborate with your team:");
         println!();
         println!("1. Push to Git repository:");
-        println!("   git push origin main");
+        println!("   smolcase sync");
         println!();
-        println!("2. Team members clone and configure:");
-        println!("   git clone <repo-url>");
-        println!("   smolcase configure");
+        println!("2. Invite team members (as admin):");
+        println!("   smolcase user invite alice");
         println!();
-        println!("3. Add team members (as admin):");
-        println!("   smolcase user add alice");
+        println!("3. Team members clone and join:");
+        println!("   git clone <repo-url>");
+        println!("   smolcase join alice.smol");
         println!("   smolcase group create developers");
         println!();
         println!("4. Set secret permissions:");
@@ -157,7 +160,7 @@ pub async fn execute() -> Result<()> {
         println!("  smolcase add ssl-cert.pem --users admin");
         println!();
         println!("• Git integration:");
-        println!("  smolcase sync  # Commit changes");
+        println!("  smolcase sync  # Commit, merge remote changes and push");
     })?;
 
     // Step 9: Security best practices
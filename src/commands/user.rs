@@ -1,15 +1,22 @@
 use crate::UserAction;
+use crate::access::AccessTracker;
+use crate::audit_log::AuditLog;
+use crate::commands::passwd::{MIN_PASSWORD_LEN, prompt_new_password};
+use crate::commands::sync::auto_commit;
 use crate::config::ConfigManager;
 use crate::credential_manager::CredentialManager;
 use crate::crypto::CryptoManager;
-use crate::types::User;
+use crate::signing::VaultSigner;
+use crate::types::{Invite, User};
 use crate::ui::UI;
 use anyhow::{Result, anyhow};
-use chrono::Utc;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use colored::*;
+use std::fs;
+use std::path::PathBuf;
 use uuid::Uuid;
 
-pub async fn execute(action: UserAction) -> Result<()> {
+pub async fn execute(action: UserAction, no_commit: bool) -> Result<()> {
     let public_config = ConfigManager::load_public_config()?;
     let cached_creds = CredentialManager::load_credentials()?;
 
@@ -26,17 +33,27 @@ pub async fn execute(action: UserAction) -> Result<()> {
 
     let master_key = CredentialManager::get_master_key(&cached_creds)?;
     let (_, mut private_config) = ConfigManager::load_full_config(&master_key)?;
+    let actor = cached_creds
+        .username
+        .clone()
+        .unwrap_or_else(|| "admin".to_string());
+    let mutating = !matches!(action, UserAction::List);
 
     match action {
-        UserAction::Add { username, email } => {
+        UserAction::Add {
+            username,
+            email,
+            expires,
+        } => {
             if private_config.users.contains_key(&username) {
                 return Err(anyhow!("User '{}' already exists", username));
             }
+            let expires_at = expires.as_deref().map(parse_expiry).transpose()?;
 
             let password = CryptoManager::generate_password();
             let (password_hash, salt) = CryptoManager::hash_password(&password)?;
 
-            let user = User {
+            let mut user = User {
                 id: Uuid::new_v4(),
                 username: username.clone(),
                 email,
@@ -45,17 +62,131 @@ pub async fn execute(action: UserAction) -> Result<()> {
                 created_at: Utc::now().to_rfc3339(),
                 last_access: None,
                 is_admin: false,
+                invite_token_hash: None,
+                expires_at,
+                disabled: false,
+                public_key: None,
+                signing_key: None,
             };
+            VaultSigner::new_user_key(&mut user, &password)?;
 
             private_config.users.insert(username.clone(), user);
             ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(&master_key, &actor, "user.add", &[], Some(username.clone()));
 
             UI::success(&format!("User '{}' created successfully!", username));
             UI::info(&format!("Generated password: {}", password));
             UI::warning("Share this password securely with the user");
+            UI::info("Tip: 'smolcase user invite' lets users choose their own password");
         }
 
-        UserAction::Remove { username } => {
+        UserAction::Invite {
+            username,
+            email,
+            output,
+            expires_in,
+            expires,
+        } => {
+            let expires_at = expires.as_deref().map(parse_expiry).transpose()?;
+            let token = CryptoManager::generate_password();
+            let (token_hash, _) = CryptoManager::hash_password(&token)?;
+
+            // The account keeps an unusable random password until the invite is redeemed
+            let (password_hash, salt) =
+                CryptoManager::hash_password(&CryptoManager::generate_password())?;
+
+            let signing_key = if let Some(user) = private_config.users.get_mut(&username) {
+                if user.is_admin {
+                    return Err(anyhow!("Cannot invite admin user '{}'", username));
+                }
+                if !UI::confirm(&format!(
+                    "User '{}' already exists. Reset their password and send a new invite?",
+                    username
+                ))? {
+                    return Ok(());
+                }
+                user.password_hash = password_hash;
+                user.salt = salt;
+                user.invite_token_hash = Some(token_hash);
+                if email.is_some() {
+                    user.email = email;
+                }
+                if expires_at.is_some() {
+                    user.expires_at = expires_at;
+                }
+                VaultSigner::new_invite_key(user)
+            } else {
+                let mut user = User {
+                    id: Uuid::new_v4(),
+                    username: username.clone(),
+                    email,
+                    password_hash,
+                    salt,
+                    created_at: Utc::now().to_rfc3339(),
+                    last_access: None,
+                    is_admin: false,
+                    invite_token_hash: Some(token_hash),
+                    expires_at,
+                    disabled: false,
+                    public_key: None,
+                    signing_key: None,
+                };
+                let signing_key = VaultSigner::new_invite_key(&mut user);
+                private_config.users.insert(username.clone(), user);
+                signing_key
+            };
+
+            let now = Utc::now();
+            let invite = Invite {
+                project_name: public_config.project_name.clone(),
+                username: username.clone(),
+                master_key: master_key.clone(),
+                token,
+                created_at: now.to_rfc3339(),
+                expires_at: (now + Duration::hours(expires_in as i64)).to_rfc3339(),
+                signing_key: Some(signing_key),
+            };
+
+            let passphrase = prompt_new_password("Invite passphrase", MIN_PASSWORD_LEN)?;
+            let encrypted_invite =
+                CryptoManager::encrypt_data_with_salt(&serde_json::to_vec(&invite)?, &passphrase)?;
+
+            let output_path = output.unwrap_or_else(|| PathBuf::from(format!("{}.smol", username)));
+            if output_path.exists() {
+                return Err(anyhow!(
+                    "Invite file already exists: {}",
+                    output_path.display()
+                ));
+            }
+
+            ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(
+                &master_key,
+                &actor,
+                "user.invite",
+                &[],
+                Some(username.clone()),
+            );
+            fs::write(
+                &output_path,
+                serde_json::to_string_pretty(&encrypted_invite)?,
+            )
+            .map_err(|e| anyhow!("Failed to write invite file: {}", e))?;
+
+            UI::success(&format!(
+                "Invite for '{}' written to {}",
+                username,
+                output_path.display()
+            ));
+            UI::info(&format!("The invite expires in {} hours", expires_in));
+            UI::warning("Share the invite file and the passphrase through separate channels");
+            UI::info(&format!(
+                "Commit .smolcase.yml, then the user runs 'smolcase join {}'",
+                output_path.display()
+            ));
+        }
+
+        UserAction::Remove { username, force } => {
             if !private_config.users.contains_key(&username) {
                 return Err(anyhow!("User '{}' not found", username));
             }
@@ -64,27 +195,66 @@ pub async fn execute(action: UserAction) -> Result<()> {
                 return Err(anyhow!("Cannot remove admin user"));
             }
 
+            let references = private_config.user_references(&username);
+            if !references.is_empty() && !force {
+                UI::warning(&format!("User '{}' is still referenced:", username));
+                for reference in &references {
+                    println!("   • {}", reference);
+                }
+                return Err(anyhow!(
+                    "Refusing to remove referenced user. Use --force to remove the references too."
+                ));
+            }
+
             if !UI::confirm(&format!("Remove user '{}'?", username))? {
                 return Ok(());
             }
 
             private_config.users.remove(&username);
+            let changes = private_config.repair_references();
             ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(
+                &master_key,
+                &actor,
+                "user.remove",
+                &[],
+                Some(username.clone()),
+            );
 
+            for change in &changes {
+                UI::info(change);
+            }
             UI::success(&format!("User '{}' removed successfully!", username));
         }
 
         UserAction::List => {
             UI::header("Users");
 
+            let access_log = AccessTracker::load(&master_key).unwrap_or_else(|e| {
+                UI::warning(&format!("Could not read access log: {}", e));
+                Default::default()
+            });
+
             for (username, user) in &private_config.users {
                 let role = if user.is_admin { "admin" } else { "user" };
-                let last_access = user.last_access.as_deref().unwrap_or("never");
+                let last_access = access_log
+                    .users
+                    .get(username)
+                    .map(|r| format!("{} ({} reads)", r.last_access, r.count))
+                    .or_else(|| user.last_access.clone())
+                    .unwrap_or_else(|| "never".to_string());
+                let status = user.status();
+                let status = if user.disabled || user.is_expired() {
+                    status.red().to_string()
+                } else {
+                    status
+                };
 
                 println!(
-                    "{} {} ({})",
+                    "{} {} ({}, {})",
                     if user.is_admin { "👑" } else { "👤" },
                     username.cyan(),
+                    status,
                     format!("{}, last access: {}", role, last_access).dimmed()
                 );
             }
@@ -101,15 +271,141 @@ pub async fn execute(action: UserAction) -> Result<()> {
             if let Some(user) = private_config.users.get_mut(&username) {
                 user.password_hash = password_hash;
                 user.salt = salt;
+                user.invite_token_hash = None;
+                VaultSigner::new_user_key(user, &new_password)?;
             }
 
             ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(
+                &master_key,
+                &actor,
+                "user.reset",
+                &[],
+                Some(username.clone()),
+            );
 
             UI::success(&format!("Password reset for user '{}'", username));
             UI::info(&format!("New password: {}", new_password));
             UI::warning("Share this password securely with the user");
         }
+
+        UserAction::Suspend { username } => {
+            let user = private_config
+                .users
+                .get_mut(&username)
+                .ok_or_else(|| anyhow!("User '{}' not found", username))?;
+
+            if user.is_admin {
+                return Err(anyhow!("Cannot suspend admin user"));
+            }
+            if user.disabled {
+                UI::warning(&format!("User '{}' is already suspended", username));
+                return Ok(());
+            }
+
+            user.disabled = true;
+            ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(
+                &master_key,
+                &actor,
+                "user.suspend",
+                &[],
+                Some(username.clone()),
+            );
+
+            UI::success(&format!("User '{}' suspended", username));
+        }
+
+        UserAction::Resume { username } => {
+            let user = private_config
+                .users
+                .get_mut(&username)
+                .ok_or_else(|| anyhow!("User '{}' not found", username))?;
+
+            if !user.disabled {
+                UI::warning(&format!("User '{}' is not suspended", username));
+                return Ok(());
+            }
+
+            user.disabled = false;
+            let expired = user.is_expired();
+            ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(
+                &master_key,
+                &actor,
+                "user.resume",
+                &[],
+                Some(username.clone()),
+            );
+
+            UI::success(&format!("User '{}' resumed", username));
+            if expired {
+                UI::warning(&format!(
+                    "User '{}' has expired. Use 'smolcase user expiry {} <DATE>' to extend access.",
+                    username, username
+                ));
+            }
+        }
+
+        UserAction::Expiry { username, expires } => {
+            let user = private_config
+                .users
+                .get_mut(&username)
+                .ok_or_else(|| anyhow!("User '{}' not found", username))?;
+
+            if user.is_admin {
+                return Err(anyhow!("Admin accounts cannot expire"));
+            }
+
+            user.expires_at = if expires == "never" {
+                None
+            } else {
+                Some(parse_expiry(&expires)?)
+            };
+            let message = match &user.expires_at {
+                Some(expires_at) => format!("User '{}' expires at {}", username, expires_at),
+                None => format!("User '{}' no longer expires", username),
+            };
+            ConfigManager::save_config(&public_config, &private_config, &master_key)?;
+            AuditLog::record_or_warn(
+                &master_key,
+                &actor,
+                "user.expiry",
+                &[],
+                Some(format!("{}: {}", username, expires)),
+            );
+
+            UI::success(&message);
+        }
+    }
+
+    if mutating {
+        auto_commit(
+            &public_config.settings,
+            &master_key,
+            cached_creds.username.as_deref(),
+            no_commit,
+        );
     }
 
     Ok(())
 }
+
+/// Accepts an RFC 3339 timestamp or a plain `YYYY-MM-DD` date (valid through the end of that day, UTC).
+fn parse_expiry(input: &str) -> Result<String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(input) {
+        return Ok(timestamp.with_timezone(&Utc).to_rfc3339());
+    }
+
+    let date = NaiveDate::parse_from_str(input, "%Y-%m-%d").map_err(|_| {
+        anyhow!(
+            "Invalid expiry '{}'. Use YYYY-MM-DD or an RFC 3339 timestamp.",
+            input
+        )
+    })?;
+    let end_of_day = date
+        .and_hms_opt(23, 59, 59)
+        .ok_or_else(|| anyhow!("Invalid expiry '{}'", input))?;
+
+    Ok(end_of_day.and_utc().to_rfc3339())
+}
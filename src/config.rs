@@ -1,9 +1,13 @@
+use crate::credential_manager::CredentialManager;
 use crate::crypto::CryptoManager;
-use crate::types::{EncryptedData, PrivateConfig, SmolcaseConfig};
+use crate::signing::VaultSigner;
+use crate::storage::Storage;
+use crate::types::{EncryptedData, LocalCredentials, PrivateConfig, SmolcaseConfig};
 use anyhow::{Result, anyhow};
-use serde_yaml;
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use uuid::Uuid;
 
 pub const CONFIG_FILE: &str = ".smolcase.yml";
 pub const CONFIG_DIR: &str = ".smolcase";
@@ -25,29 +29,160 @@ impl ConfigManager {
 
     pub fn is_smolcase_project() -> bool {
         Self::config_path().map(|p| p.exists()).unwrap_or(false)
+            || Storage::config_path().map(|p| p.exists()).unwrap_or(false)
     }
 
     pub fn load_public_config() -> Result<SmolcaseConfig> {
-        let config_path = Self::config_path()?;
+        let content = Self::load_raw()?
+            .ok_or_else(|| anyhow!("Not a smolcase project. Run 'smolcase init' first."))?;
 
-        if !config_path.exists() {
+        Self::parse_config(&content)
+    }
+
+    /// The vault file as stored by the configured backend.
+    pub fn load_raw() -> Result<Option<String>> {
+        Storage::open()?
+            .load(CONFIG_FILE)
+            .map_err(|e| anyhow!("Failed to read config file: {}", e))
+    }
+
+    pub fn parse_config(content: &str) -> Result<SmolcaseConfig> {
+        serde_yaml::from_str(content).map_err(|e| anyhow!("Invalid config file: {}", e))
+    }
+
+    pub fn load_full_config(master_key: &str) -> Result<(SmolcaseConfig, PrivateConfig)> {
+        let public_config = Self::load_public_config()?;
+        let private_config = Self::decrypt_private_config(&public_config, master_key)?;
+        Self::check_local_trust(&public_config, &private_config)?;
+
+        Ok((public_config, private_config))
+    }
+
+    /// The first signed vault seen on this machine pins its public key, so a
+    /// vault with the signature stripped or the key swapped is rejected. The
+    /// highest revision and membership counters seen are remembered too, so an
+    /// old vault restored from Git can't bring back revoked users, old secret
+    /// values or a member list a group manager signed earlier.
+    fn check_local_trust(
+        public_config: &SmolcaseConfig,
+        private_config: &PrivateConfig,
+    ) -> Result<()> {
+        let mut cached_creds = CredentialManager::load_credentials()?;
+        if Self::update_trust(public_config, private_config, &mut cached_creds)? {
+            CredentialManager::save_credentials(&cached_creds)?;
+        }
+
+        Ok(())
+    }
+
+    /// The checks of `check_local_trust` against `cached_creds`, which are
+    /// moved forward in place. Returns whether they changed.
+    fn update_trust(
+        public_config: &SmolcaseConfig,
+        private_config: &PrivateConfig,
+        cached_creds: &mut LocalCredentials,
+    ) -> Result<bool> {
+        let mut changed = false;
+
+        match (
+            &cached_creds.vault_public_key,
+            &public_config.admin_public_key,
+        ) {
+            (Some(pinned), Some(current)) if pinned == current => {}
+            (Some(_), _) => {
+                return Err(anyhow!(
+                    "The vault's admin signing key was removed or replaced since this machine last used it. \
+                     If an admin really reset it, run 'smolcase logout' and 'smolcase configure' again."
+                ));
+            }
+            (None, Some(current)) => {
+                cached_creds.vault_public_key = Some(current.clone());
+                changed = true;
+            }
+            (None, None) => {}
+        }
+
+        if public_config.revision < cached_creds.vault_revision {
             return Err(anyhow!(
-                "Not a smolcase project. Run 'smolcase init' first."
+                "Vault revision {} is older than revision {} already used on this machine; \
+                 .smolcase.yml may have been rolled back. Pull the latest vault, or ask an admin \
+                 to review it and run 'smolcase admin sign' to accept it.",
+                public_config.revision,
+                cached_creds.vault_revision
             ));
         }
+        if public_config.revision > cached_creds.vault_revision {
+            cached_creds.vault_revision = public_config.revision;
+            changed = true;
+        }
 
-        let content = fs::read_to_string(&config_path)
-            .map_err(|e| anyhow!("Failed to read config file: {}", e))?;
+        let rolled_back = Self::rolled_back_groups(private_config, cached_creds);
+        if !rolled_back.is_empty() {
+            return Err(anyhow!(
+                "Members of group(s) {} were signed before the ones already used on this machine; \
+                 .smolcase.yml may have been rolled back. Pull the latest vault, or ask an admin \
+                 to review it and run 'smolcase admin sign' to accept it.",
+                rolled_back.join(", ")
+            ));
+        }
+        for (id, counter) in Self::membership_counters(private_config) {
+            let seen = cached_creds.membership_counters.entry(id).or_default();
+            if counter > *seen {
+                *seen = counter;
+                changed = true;
+            }
+        }
 
-        let config: SmolcaseConfig =
-            serde_yaml::from_str(&content).map_err(|e| anyhow!("Invalid config file: {}", e))?;
+        Ok(changed)
+    }
 
-        Ok(config)
+    /// Delegated groups whose members carry an older signature than the one
+    /// this machine already used.
+    pub fn rolled_back_groups(
+        private_config: &PrivateConfig,
+        cached_creds: &LocalCredentials,
+    ) -> Vec<String> {
+        let mut groups: Vec<String> = private_config
+            .groups
+            .values()
+            .filter(|group| {
+                private_config.is_delegated(&group.name)
+                    && cached_creds
+                        .membership_counters
+                        .get(&group.id)
+                        .is_some_and(|seen| VaultSigner::membership_counter(group) < *seen)
+            })
+            .map(|group| group.name.clone())
+            .collect();
+        groups.sort();
+        groups
     }
 
-    pub fn load_full_config(master_key: &str) -> Result<(SmolcaseConfig, PrivateConfig)> {
-        let public_config = Self::load_public_config()?;
+    /// The membership counter of every delegated group, by group id.
+    pub fn membership_counters(private_config: &PrivateConfig) -> HashMap<Uuid, u64> {
+        private_config
+            .groups
+            .values()
+            .filter(|group| private_config.is_delegated(&group.name))
+            .map(|group| (group.id, VaultSigner::membership_counter(group)))
+            .collect()
+    }
+
+    pub fn decrypt_private_config(
+        public_config: &SmolcaseConfig,
+        master_key: &str,
+    ) -> Result<PrivateConfig> {
+        let private_config = Self::decrypt_unverified(public_config, master_key)?;
+        VaultSigner::verify(public_config, &private_config)?;
 
+        Ok(private_config)
+    }
+
+    /// Decrypts without checking the admin signature. Only for re-signing after review.
+    pub fn decrypt_unverified(
+        public_config: &SmolcaseConfig,
+        master_key: &str,
+    ) -> Result<PrivateConfig> {
         if !CryptoManager::verify_password(master_key, &public_config.master_key_hash)? {
             return Err(anyhow!("Invalid master key"));
         }
@@ -65,7 +200,7 @@ impl ConfigManager {
             serde_json::from_slice(&private_data)?
         };
 
-        Ok((public_config, private_config))
+        Ok(private_config)
     }
 
     pub fn save_config(
@@ -73,26 +208,73 @@ impl ConfigManager {
         private_config: &PrivateConfig,
         master_key: &str,
     ) -> Result<()> {
+        // Changes to admin-controlled content move the revision forward and
+        // need a fresh signature; so do delegated members no manager signed
+        let mut public_config = public_config.clone();
+        let mut private_config = private_config.clone();
+        let original_revision = public_config.revision;
+        let original_data = public_config.encrypted_data.clone();
+        let mut cached_creds = CredentialManager::load_credentials()?;
+        let signed = VaultSigner::is_signed(&public_config);
+        let content_changed =
+            !signed || VaultSigner::verify_content(&public_config, &private_config).is_err();
+        if content_changed {
+            public_config.revision += 1;
+        }
+        if signed
+            && (content_changed
+                || !VaultSigner::unsigned_groups(&public_config, &private_config).is_empty())
+        {
+            let admin_password = CredentialManager::get_admin_password(&cached_creds)?;
+            VaultSigner::sign(&mut public_config, &mut private_config, &admin_password)?;
+        }
+
+        let content = Self::render_config(&public_config, &private_config, master_key)?;
+
+        let storage = Storage::open()?;
+        let _lock = storage.lock()?;
+        // Someone else saved the vault since this one was loaded. Every save
+        // re-encrypts with a fresh salt, so this also catches writes that
+        // don't move the revision, such as password and membership changes.
+        if let Some(stored) = storage.load(CONFIG_FILE)?
+            && let Ok(stored) = Self::parse_config(&stored)
+            && (stored.revision > original_revision
+                || stored.encrypted_data.salt != original_data.salt
+                || stored.encrypted_data.data != original_data.data)
+        {
+            return Err(anyhow!(
+                "The vault was changed elsewhere (now at revision {}) while this command ran. Run it again.",
+                stored.revision
+            ));
+        }
+        storage
+            .save(CONFIG_FILE, &content)
+            .map_err(|e| anyhow!("Failed to write config file: {}", e))?;
+
+        if public_config.revision > cached_creds.vault_revision {
+            cached_creds.vault_revision = public_config.revision;
+            CredentialManager::save_credentials(&cached_creds)?;
+        }
+
+        Ok(())
+    }
+
+    /// Encrypts the private config and serializes the vault file without writing it.
+    pub fn render_config(
+        public_config: &SmolcaseConfig,
+        private_config: &PrivateConfig,
+        master_key: &str,
+    ) -> Result<String> {
         let private_data = serde_json::to_vec(private_config)?;
         let encrypted_data = CryptoManager::encrypt_data_with_salt(&private_data, master_key)?;
 
         let final_config = SmolcaseConfig {
-            version: public_config.version.clone(),
-            project_name: public_config.project_name.clone(),
-            created_at: public_config.created_at.clone(),
-            admin_key_hash: public_config.admin_key_hash.clone(),
-            master_key_hash: public_config.master_key_hash.clone(),
             encrypted_data,
+            ..public_config.clone()
         };
 
-        let config_path = Self::config_path()?;
-        let content = serde_yaml::to_string(&final_config)
-            .map_err(|e| anyhow!("Failed to serialize config: {}", e))?;
-
-        fs::write(&config_path, content)
-            .map_err(|e| anyhow!("Failed to write config file: {}", e))?;
-
-        Ok(())
+        serde_yaml::to_string(&final_config)
+            .map_err(|e| anyhow!("Failed to serialize config: {}", e))
     }
 
     pub fn create_config_dir() -> Result<()> {
@@ -106,3 +288,137 @@ impl ConfigManager {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Group, MembershipSignature};
+
+    fn public_config(revision: u64, admin_public_key: &str) -> SmolcaseConfig {
+        SmolcaseConfig {
+            version: "1.0.0".to_string(),
+            project_name: "demo".to_string(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            admin_key_hash: String::new(),
+            master_key_hash: String::new(),
+            encrypted_data: EncryptedData::default(),
+            settings: Default::default(),
+            revision,
+            admin_public_key: Some(admin_public_key.to_string()),
+            admin_signing_key: None,
+            signature: None,
+        }
+    }
+
+    /// A vault with delegated group "qa" whose members were signed with `counter`.
+    fn private_config(counter: u64) -> PrivateConfig {
+        let qa = Group {
+            id: Uuid::from_u128(1),
+            name: "qa".to_string(),
+            description: None,
+            members: vec!["bob".to_string()],
+            subgroups: Vec::new(),
+            managers: vec!["alice".to_string()],
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            membership_signature: Some(MembershipSignature {
+                manager: Some("alice".to_string()),
+                signature: String::new(),
+                counter,
+            }),
+        };
+
+        PrivateConfig {
+            users: HashMap::new(),
+            groups: HashMap::from([("qa".to_string(), qa)]),
+            secrets: HashMap::new(),
+            encrypted_secrets: EncryptedData::default(),
+        }
+    }
+
+    #[test]
+    fn remembers_key_revision_and_membership() {
+        let mut cached_creds = LocalCredentials::default();
+
+        let changed = ConfigManager::update_trust(
+            &public_config(3, "key"),
+            &private_config(2),
+            &mut cached_creds,
+        )
+        .unwrap();
+
+        assert!(changed);
+        assert_eq!(cached_creds.vault_public_key.as_deref(), Some("key"));
+        assert_eq!(cached_creds.vault_revision, 3);
+        assert_eq!(cached_creds.membership_counters[&Uuid::from_u128(1)], 2);
+
+        // Loading the same vault again changes nothing
+        assert!(
+            !ConfigManager::update_trust(
+                &public_config(3, "key"),
+                &private_config(2),
+                &mut cached_creds,
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_a_lower_revision() {
+        let mut cached_creds = LocalCredentials::default();
+        ConfigManager::update_trust(
+            &public_config(5, "key"),
+            &private_config(1),
+            &mut cached_creds,
+        )
+        .unwrap();
+
+        let error = ConfigManager::update_trust(
+            &public_config(4, "key"),
+            &private_config(1),
+            &mut cached_creds,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("older than revision 5"));
+        assert_eq!(cached_creds.vault_revision, 5);
+    }
+
+    #[test]
+    fn rejects_a_replayed_member_list() {
+        // Manager-signed membership changes keep the revision, so only the counter tells
+        let mut cached_creds = LocalCredentials::default();
+        ConfigManager::update_trust(
+            &public_config(5, "key"),
+            &private_config(3),
+            &mut cached_creds,
+        )
+        .unwrap();
+
+        let error = ConfigManager::update_trust(
+            &public_config(5, "key"),
+            &private_config(2),
+            &mut cached_creds,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("Members of group(s) qa"));
+    }
+
+    #[test]
+    fn rejects_a_replaced_signing_key() {
+        let mut cached_creds = LocalCredentials::default();
+        ConfigManager::update_trust(
+            &public_config(5, "key"),
+            &private_config(1),
+            &mut cached_creds,
+        )
+        .unwrap();
+
+        assert!(
+            ConfigManager::update_trust(
+                &public_config(6, "other-key"),
+                &private_config(1),
+                &mut cached_creds,
+            )
+            .is_err()
+        );
+    }
+}
@@ -1,11 +1,10 @@
 use crate::config::ConfigManager;
 use crate::crypto::CryptoManager;
-use crate::types::LocalCredentials;
+use crate::types::{LocalCredentials, PrivateConfig};
 use crate::ui::UI;
 use anyhow::{Result, anyhow};
-use serde_json;
 use std::fs;
-use std::io::{self, Write};
+use std::io;
 use std::path::PathBuf;
 
 pub struct CredentialManager;
@@ -72,38 +71,60 @@ impl CredentialManager {
     }
 
     pub fn get_admin_password(cached_creds: &LocalCredentials) -> Result<String, io::Error> {
-        if let Some(password) = &cached_creds.admin_password {
-            if !password.is_empty() {
-                return Ok(password.clone());
-            }
+        if let Some(password) = &cached_creds.admin_password
+            && !password.is_empty()
+        {
+            return Ok(password.clone());
         }
         UI::password("Admin password")
     }
 
     pub fn get_user_password(cached_creds: &LocalCredentials) -> Result<String, io::Error> {
-        if let Some(password) = &cached_creds.user_password {
-            if !password.is_empty() {
-                return Ok(password.clone());
-            }
+        if let Some(password) = &cached_creds.user_password
+            && !password.is_empty()
+        {
+            return Ok(password.clone());
         }
         UI::password("Your password")
     }
 
     pub fn get_master_key(cached_creds: &LocalCredentials) -> Result<String, io::Error> {
-        if let Some(key) = &cached_creds.master_key {
-            if !key.is_empty() {
-                return Ok(key.clone());
-            }
+        if let Some(key) = &cached_creds.master_key
+            && !key.is_empty()
+        {
+            return Ok(key.clone());
         }
         UI::password("Master decryption key")
     }
 
     pub fn get_username(cached_creds: &LocalCredentials) -> Result<String, io::Error> {
-        if let Some(username) = &cached_creds.username {
-            if !username.is_empty() {
+        if let Some(username) = &cached_creds.username
+            && !username.is_empty()
+        {
+            return Ok(username.clone());
+        }
+        UI::input("Username")
+    }
+
+    /// The user `password` belongs to, trying the cached username first.
+    /// Fails if no user has this password or theirs is disabled or expired.
+    pub fn authenticate_user(
+        cached_creds: &LocalCredentials,
+        private_config: &PrivateConfig,
+        password: &str,
+    ) -> Result<String> {
+        let cached = cached_creds
+            .username
+            .as_ref()
+            .and_then(|name| private_config.users.get_key_value(name));
+
+        for (username, user) in cached.into_iter().chain(&private_config.users) {
+            if CryptoManager::verify_password(password, &user.password_hash)? {
+                user.ensure_active()?;
                 return Ok(username.clone());
             }
         }
-        UI::input("Username")
+
+        Err(anyhow!("Invalid password"))
     }
 }
@@ -8,10 +8,9 @@ use chacha20poly1305::{
     aead::{Aead, KeyInit, OsRng as ChaChaOsRng},
 };
 use rand::RngCore;
-use serde::{Deserialize, Serialize};
 
 const NONCE_SIZE: usize = 12;
-const KEY_SIZE: usize = 32;
+pub const KEY_SIZE: usize = 32;
 const SALT_SIZE: usize = 32;
 
 pub struct CryptoManager;
@@ -38,7 +37,7 @@ impl CryptoManager {
             .is_ok())
     }
 
-    fn derive_key_with_salt(password: &str, salt: &[u8]) -> Result<[u8; KEY_SIZE]> {
+    pub fn derive_key_with_salt(password: &str, salt: &[u8]) -> Result<[u8; KEY_SIZE]> {
         if salt.len() < 16 {
             return Err(anyhow!("Salt must be at least 16 bytes"));
         }
@@ -53,7 +52,7 @@ impl CryptoManager {
         Ok(key)
     }
 
-    fn generate_salt() -> [u8; SALT_SIZE] {
+    pub fn generate_salt() -> [u8; SALT_SIZE] {
         let mut salt = [0u8; SALT_SIZE];
         OsRng.fill_bytes(&mut salt);
         salt
@@ -62,8 +61,17 @@ impl CryptoManager {
     pub fn encrypt_data_with_salt(data: &[u8], password: &str) -> Result<EncryptedData> {
         let salt = Self::generate_salt();
         let key = Self::derive_key_with_salt(password, &salt)?;
+        let encrypted_data = Self::encrypt_with_key(data, &key)?;
 
-        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
+        Ok(EncryptedData {
+            salt: BASE64.encode(salt),
+            data: BASE64.encode(encrypted_data),
+        })
+    }
+
+    /// Encrypts with an already derived key. Output is nonce followed by ciphertext.
+    pub fn encrypt_with_key(data: &[u8], key: &[u8; KEY_SIZE]) -> Result<Vec<u8>> {
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
         let mut nonce_bytes = [0u8; NONCE_SIZE];
         ChaChaOsRng.fill_bytes(&mut nonce_bytes);
         let nonce = Nonce::from_slice(&nonce_bytes);
@@ -76,10 +84,21 @@ impl CryptoManager {
         encrypted_data.extend_from_slice(&nonce_bytes);
         encrypted_data.extend_from_slice(&ciphertext);
 
-        Ok(EncryptedData {
-            salt: BASE64.encode(salt),
-            data: BASE64.encode(encrypted_data),
-        })
+        Ok(encrypted_data)
+    }
+
+    pub fn decrypt_with_key(data: &[u8], key: &[u8; KEY_SIZE]) -> Result<Vec<u8>> {
+        if data.len() < NONCE_SIZE {
+            return Err(anyhow!("Invalid encrypted data"));
+        }
+
+        let (nonce_bytes, ciphertext) = data.split_at(NONCE_SIZE);
+        let nonce = Nonce::from_slice(nonce_bytes);
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+        cipher
+            .decrypt(nonce, ciphertext)
+            .map_err(|e| anyhow!("Decryption failed: {}", e))
     }
 
     pub fn decrypt_data_with_salt(encrypted: &EncryptedData, password: &str) -> Result<Vec<u8>> {
@@ -97,19 +116,7 @@ impl CryptoManager {
             .decode(&encrypted.data)
             .map_err(|e| anyhow!("Invalid data base64: {}", e))?;
 
-        if data.len() < NONCE_SIZE {
-            return Err(anyhow!("Invalid encrypted data"));
-        }
-
-        let (nonce_bytes, ciphertext) = data.split_at(NONCE_SIZE);
-        let nonce = Nonce::from_slice(nonce_bytes);
-
-        let cipher = ChaCha20Poly1305::new(Key::from_slice(&key));
-        let plaintext = cipher
-            .decrypt(nonce, ciphertext)
-            .map_err(|e| anyhow!("Decryption failed: {}", e))?;
-
-        Ok(plaintext)
+        Self::decrypt_with_key(&data, &key)
     }
 
     pub fn generate_password() -> String {
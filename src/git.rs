@@ -1,6 +1,42 @@
+use crate::access::ACCESS_FILE;
+use crate::audit_log::AUDIT_FILE;
+use crate::config::CONFIG_FILE;
 use anyhow::{Result, anyhow};
-use git2::{Repository, Signature, Time};
-use std::path::Path;
+use chrono::DateTime;
+use git2::build::{CheckoutBuilder, RepoBuilder};
+use git2::{
+    Cred, CredentialType, Delta, FetchOptions, IndexConflict, Oid, PushOptions, RemoteCallbacks,
+    Repository, Signature, Sort, Tree,
+};
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+
+const INDEX_STAGE_MASK: u16 = 0x3000;
+
+/// A commit that changed a tracked file, with the file before and after.
+pub struct FileRevision {
+    pub id: String,
+    pub author: String,
+    pub time: String,
+    pub message: String,
+    pub content: Option<String>,
+    pub parent_content: Option<String>,
+}
+
+/// What `GitManager::pull` did to bring in remote changes.
+pub enum PullOutcome {
+    UpToDate,
+    RemoteEmpty,
+    FastForward,
+    /// Merge commit created; lists the files resolved by the caller.
+    Merged(Vec<String>),
+}
+
+/// Who a commit is attributed to. Without an email, the git config email is used.
+pub struct CommitAuthor {
+    pub name: String,
+    pub email: Option<String>,
+}
 
 pub struct GitManager;
 
@@ -12,7 +48,8 @@ impl GitManager {
         Ok(())
     }
 
-    pub fn add_and_commit(path: &Path, message: &str) -> Result<()> {
+    /// Commits the vault files as `author`, or as the git config user when `None`.
+    pub fn add_and_commit(path: &Path, message: &str, author: Option<&CommitAuthor>) -> Result<()> {
         let repo =
             Repository::open(path).map_err(|e| anyhow!("Failed to open Git repository: {}", e))?;
 
@@ -21,9 +58,17 @@ impl GitManager {
             .map_err(|e| anyhow!("Failed to get Git index: {}", e))?;
 
         index
-            .add_path(Path::new(".smolcase.yml"))
+            .add_path(Path::new(CONFIG_FILE))
             .map_err(|e| anyhow!("Failed to add file to Git index: {}", e))?;
 
+        for file in [ACCESS_FILE, AUDIT_FILE] {
+            if path.join(file).exists() {
+                index
+                    .add_path(Path::new(file))
+                    .map_err(|e| anyhow!("Failed to add file to Git index: {}", e))?;
+            }
+        }
+
         index
             .write()
             .map_err(|e| anyhow!("Failed to write Git index: {}", e))?;
@@ -36,8 +81,19 @@ impl GitManager {
             .find_tree(tree_id)
             .map_err(|e| anyhow!("Failed to find Git tree: {}", e))?;
 
-        let sig = Signature::now("smolcase", "smolcase@example.com")
-            .map_err(|e| anyhow!("Failed to create Git signature: {}", e))?;
+        let committer = Self::signature(&repo)?;
+        let author = match author {
+            Some(author) => {
+                let email = author
+                    .email
+                    .clone()
+                    .or_else(|| committer.email().map(|e| e.to_string()))
+                    .unwrap_or_else(|| format!("{}@smolcase", author.name));
+                Signature::now(&author.name, &email)
+                    .map_err(|e| anyhow!("Failed to create Git signature: {}", e))?
+            }
+            None => committer.clone(),
+        };
 
         let head = repo.head().ok();
         let parent = head
@@ -51,19 +107,587 @@ impl GitManager {
             vec![]
         };
 
-        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)
+        repo.commit(Some("HEAD"), &author, &committer, message, &tree, &parents)
+            .map_err(|e| anyhow!("Failed to create Git commit: {}", e))?;
+
+        Ok(())
+    }
+
+    /// True when any vault file differs from HEAD. Ignore rules don't apply:
+    /// `add_and_commit` adds these files by path, and vaults created before
+    /// the generated .gitignore excepted the audit log still match `*.log`.
+    pub fn has_changes(path: &Path) -> Result<bool> {
+        let repo =
+            Repository::open(path).map_err(|e| anyhow!("Failed to open Git repository: {}", e))?;
+
+        Ok([CONFIG_FILE, ACCESS_FILE, AUDIT_FILE].iter().any(|file| {
+            repo.status_file(Path::new(file))
+                .is_ok_and(|status| !status.is_empty())
+        }))
+    }
+
+    /// The remote to sync with: the current branch's upstream, else `origin`, else the only remote.
+    pub fn default_remote(path: &Path) -> Result<Option<String>> {
+        let repo =
+            Repository::open(path).map_err(|e| anyhow!("Failed to open Git repository: {}", e))?;
+
+        if let Ok(branch) = Self::current_branch(&repo)
+            && let Ok(remote) = repo.branch_upstream_remote(&format!("refs/heads/{}", branch))
+            && let Some(remote) = remote.as_str()
+        {
+            return Ok(Some(remote.to_string()));
+        }
+
+        let remotes = repo
+            .remotes()
+            .map_err(|e| anyhow!("Failed to list Git remotes: {}", e))?;
+        let names: Vec<&str> = remotes.iter().flatten().collect();
+
+        Ok(if names.contains(&"origin") {
+            Some("origin".to_string())
+        } else if names.len() == 1 {
+            Some(names[0].to_string())
+        } else {
+            None
+        })
+    }
+
+    /// Fetches `remote_name` and fast-forwards or merges its copy of the current branch.
+    ///
+    /// Conflicting files are handed to `resolve(file, base, ours, theirs)`, which
+    /// returns the merged content; vault files are encrypted, so a textual merge
+    /// never works for them.
+    pub fn pull(
+        path: &Path,
+        remote_name: &str,
+        mut resolve: impl FnMut(&str, Option<&str>, &str, &str) -> Result<String>,
+    ) -> Result<PullOutcome> {
+        let repo =
+            Repository::open(path).map_err(|e| anyhow!("Failed to open Git repository: {}", e))?;
+        let branch = Self::current_branch(&repo)?;
+
+        let config = repo
+            .config()
+            .map_err(|e| anyhow!("Failed to read Git config: {}", e))?;
+        let mut remote = repo
+            .find_remote(remote_name)
+            .map_err(|e| anyhow!("Git remote '{}' not found: {}", remote_name, e))?;
+        let mut fetch_options = FetchOptions::new();
+        fetch_options.remote_callbacks(Self::remote_callbacks(&config));
+        remote
+            .fetch(&[] as &[&str], Some(&mut fetch_options), None)
+            .map_err(|e| anyhow!("Failed to fetch from '{}': {}", remote_name, e))?;
+
+        let remote_ref = format!("refs/remotes/{}/{}", remote_name, branch);
+        let Ok(their_oid) = repo.refname_to_id(&remote_ref) else {
+            return Ok(PullOutcome::RemoteEmpty);
+        };
+
+        let annotated = repo
+            .find_annotated_commit(their_oid)
+            .map_err(|e| anyhow!("Failed to find Git commit: {}", e))?;
+        let (analysis, _) = repo
+            .merge_analysis(&[&annotated])
+            .map_err(|e| anyhow!("Failed to analyze merge: {}", e))?;
+
+        if analysis.is_up_to_date() {
+            return Ok(PullOutcome::UpToDate);
+        }
+
+        if analysis.is_fast_forward() {
+            Self::move_branch(&repo, &branch, their_oid, "smolcase: fast-forward")?;
+            return Ok(PullOutcome::FastForward);
+        }
+
+        let ours = repo
+            .head()
+            .and_then(|h| h.peel_to_commit())
+            .map_err(|e| anyhow!("Failed to read Git HEAD: {}", e))?;
+        let theirs = repo
+            .find_commit(their_oid)
+            .map_err(|e| anyhow!("Failed to find Git commit: {}", e))?;
+        let mut index = repo
+            .merge_commits(&ours, &theirs, None)
+            .map_err(|e| anyhow!("Failed to merge: {}", e))?;
+
+        let mut resolved = Vec::new();
+        if index.has_conflicts() {
+            let conflicts: Vec<IndexConflict> = index
+                .conflicts()
+                .and_then(|c| c.collect())
+                .map_err(|e| anyhow!("Failed to read merge conflicts: {}", e))?;
+
+            for conflict in conflicts {
+                let (Some(our), Some(their)) = (conflict.our, conflict.their) else {
+                    return Err(anyhow!(
+                        "Merge conflict: a file was deleted on one side. Resolve it with git."
+                    ));
+                };
+                let file = String::from_utf8_lossy(&our.path).to_string();
+
+                let base = conflict
+                    .ancestor
+                    .map(|a| Self::blob_text(&repo, a.id))
+                    .transpose()?;
+                let merged = resolve(
+                    &file,
+                    base.as_deref(),
+                    &Self::blob_text(&repo, our.id)?,
+                    &Self::blob_text(&repo, their.id)?,
+                )?;
+
+                let blob_id = repo
+                    .blob(merged.as_bytes())
+                    .map_err(|e| anyhow!("Failed to write Git blob: {}", e))?;
+                for stage in 1..=3 {
+                    let _ = index.remove(Path::new(&file), stage);
+                }
+                let mut entry = our;
+                entry.id = blob_id;
+                entry.file_size = merged.len() as u32;
+                entry.flags &= !INDEX_STAGE_MASK;
+                index
+                    .add(&entry)
+                    .map_err(|e| anyhow!("Failed to add file to Git index: {}", e))?;
+
+                resolved.push(file);
+            }
+        }
+
+        let tree_id = index
+            .write_tree_to(&repo)
+            .map_err(|e| anyhow!("Failed to write Git tree: {}", e))?;
+        let tree = repo
+            .find_tree(tree_id)
+            .map_err(|e| anyhow!("Failed to find Git tree: {}", e))?;
+        let sig = Self::signature(&repo)?;
+        let merge_id = repo
+            .commit(
+                None,
+                &sig,
+                &sig,
+                &format!("Merge {}/{} into {}", remote_name, branch, branch),
+                &tree,
+                &[&ours, &theirs],
+            )
             .map_err(|e| anyhow!("Failed to create Git commit: {}", e))?;
 
+        Self::move_branch(&repo, &branch, merge_id, "smolcase: merge")?;
+        Ok(PullOutcome::Merged(resolved))
+    }
+
+    /// Pushes the current branch and sets it to track the remote branch.
+    pub fn push(path: &Path, remote_name: &str) -> Result<()> {
+        let repo =
+            Repository::open(path).map_err(|e| anyhow!("Failed to open Git repository: {}", e))?;
+        let branch = Self::current_branch(&repo)?;
+
+        let config = repo
+            .config()
+            .map_err(|e| anyhow!("Failed to read Git config: {}", e))?;
+        let mut remote = repo
+            .find_remote(remote_name)
+            .map_err(|e| anyhow!("Git remote '{}' not found: {}", remote_name, e))?;
+
+        let mut rejected = None;
+        {
+            let mut callbacks = Self::remote_callbacks(&config);
+            callbacks.push_update_reference(|refname, status| {
+                if let Some(status) = status {
+                    rejected = Some(format!("{}: {}", refname, status));
+                }
+                Ok(())
+            });
+            let mut push_options = PushOptions::new();
+            push_options.remote_callbacks(callbacks);
+
+            let refspec = format!("refs/heads/{0}:refs/heads/{0}", branch);
+            remote
+                .push(&[refspec.as_str()], Some(&mut push_options))
+                .map_err(|e| anyhow!("Failed to push to '{}': {}", remote_name, e))?;
+        }
+
+        if let Some(reason) = rejected {
+            return Err(anyhow!(
+                "Push rejected ({}). Run 'smolcase sync' again.",
+                reason
+            ));
+        }
+
+        if let Ok(mut local) = repo.find_branch(&branch, git2::BranchType::Local)
+            && local.upstream().is_err()
+        {
+            let _ = local.set_upstream(Some(&format!("{}/{}", remote_name, branch)));
+        }
+
         Ok(())
     }
 
-    pub fn clone_repo(url: &str, path: &Path) -> Result<()> {
-        Repository::clone(url, path).map_err(|e| anyhow!("Failed to clone repository: {}", e))?;
+    /// Clones over HTTPS, SSH (including `git@host:org/repo`), `file://` or a
+    /// local path, optionally checking out `branch` instead of the default.
+    pub fn clone_repo(url: &str, path: &Path, branch: Option<&str>) -> Result<()> {
+        let config = git2::Config::open_default()?;
+        let mut fetch_options = FetchOptions::new();
+        fetch_options.remote_callbacks(Self::remote_callbacks(&config));
+
+        let mut builder = RepoBuilder::new();
+        builder.fetch_options(fetch_options);
+        if let Some(branch) = branch {
+            builder.branch(branch);
+        }
+
+        builder
+            .clone(url, path)
+            .map_err(|e| anyhow!("Failed to clone repository: {}", e.message()))?;
 
         Ok(())
     }
 
+    /// Whether `repo` names something to clone rather than a local directory.
+    pub fn is_remote_url(repo: &str) -> bool {
+        // scp-like SSH syntax: user@host:path
+        let scp_like = repo
+            .split_once(':')
+            .is_some_and(|(host, _)| host.contains('@') && !host.contains('/'));
+
+        repo.contains("://") || scp_like
+    }
+
     pub fn is_git_repo(path: &Path) -> bool {
         Repository::open(path).is_ok()
     }
+
+    /// Like `is_git_repo`, but also true anywhere below a repository's root.
+    pub fn is_inside_git_repo(path: &Path) -> bool {
+        Repository::discover(path).is_ok()
+    }
+
+    /// The commit HEAD points to, if there is one.
+    pub fn head_commit(path: &Path) -> Option<String> {
+        let repo = Repository::open(path).ok()?;
+        let commit = repo.head().ok()?.peel_to_commit().ok()?;
+        Some(commit.id().to_string())
+    }
+
+    /// Files added or modified in the index, with their staged content.
+    pub fn staged_files(path: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+        let repo = Repository::discover(path)
+            .map_err(|e| anyhow!("Failed to open Git repository: {}", e))?;
+
+        let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        let index = repo.index()?;
+        let diff = repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None)?;
+
+        let mut files = Vec::new();
+        for delta in diff.deltas() {
+            if delta.status() == Delta::Deleted {
+                continue;
+            }
+
+            let file = delta.new_file();
+            if let Some(name) = file.path().and_then(|p| p.to_str())
+                && let Ok(blob) = repo.find_blob(file.id())
+            {
+                files.push((name.to_string(), blob.content().to_vec()));
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// Files under `root` that Git doesn't ignore, with the name to report for
+    /// each: relative to the repository root, or to `root` outside a repository.
+    pub fn working_files(root: &Path) -> Result<Vec<(String, PathBuf)>> {
+        let root = root
+            .canonicalize()
+            .map_err(|e| anyhow!("Cannot read {}: {}", root.display(), e))?;
+        let repo = Repository::discover(&root).ok();
+        let base = repo
+            .as_ref()
+            .and_then(|r| r.workdir())
+            .and_then(|w| w.canonicalize().ok())
+            .unwrap_or_else(|| {
+                if root.is_file() {
+                    root.parent().unwrap_or(&root).to_path_buf()
+                } else {
+                    root.clone()
+                }
+            });
+
+        let mut files = Vec::new();
+        let mut pending = vec![root];
+        while let Some(current) = pending.pop() {
+            let Ok(relative) = current.strip_prefix(&base) else {
+                continue;
+            };
+            if relative.file_name().is_some_and(|name| name == ".git") {
+                continue;
+            }
+            if let Some(repo) = &repo
+                && !relative.as_os_str().is_empty()
+                && repo.is_path_ignored(relative).unwrap_or(false)
+            {
+                continue;
+            }
+
+            if current.is_dir() {
+                for entry in std::fs::read_dir(&current)? {
+                    let entry = entry?;
+                    if !entry.file_type()?.is_symlink() {
+                        pending.push(entry.path());
+                    }
+                }
+            } else if current.is_file() {
+                files.push((relative.to_string_lossy().replace('\\', "/"), current));
+            }
+        }
+
+        files.sort();
+        Ok(files)
+    }
+
+    /// Calls `visit(commit, file, line_number, line)` for every line added by
+    /// a commit reachable from any local branch, oldest first.
+    pub fn added_lines(path: &Path, mut visit: impl FnMut(&str, &str, usize, &str)) -> Result<()> {
+        let repo = Repository::discover(path)
+            .map_err(|e| anyhow!("Failed to open Git repository: {}", e))?;
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+        if repo.head().is_ok() {
+            revwalk.push_head()?;
+        }
+        revwalk.push_glob("refs/heads/*")?;
+
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            // Merges are diffed against their first parent, so lines written while
+            // resolving conflicts are seen; lines from the merged branch were already
+            // visited at their own commits
+            let parent_tree = match commit.parent(0) {
+                Ok(parent) => Some(parent.tree()?),
+                Err(_) => None,
+            };
+            let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            let id = commit.id().to_string()[..8].to_string();
+
+            diff.foreach(
+                &mut |_, _| true,
+                None,
+                None,
+                Some(&mut |delta, _, line| {
+                    if line.origin() == '+'
+                        && let (Some(file), Some(number)) =
+                            (delta.new_file().path(), line.new_lineno())
+                    {
+                        let text = String::from_utf8_lossy(line.content());
+                        visit(
+                            &id,
+                            &file.to_string_lossy(),
+                            number as usize,
+                            text.trim_end_matches(['\r', '\n']),
+                        );
+                    }
+                    true
+                }),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Where Git looks for hooks, honouring `core.hooksPath`.
+    pub fn hooks_dir(path: &Path) -> Result<PathBuf> {
+        let repo = Repository::discover(path)
+            .map_err(|e| anyhow!("Failed to open Git repository: {}", e))?;
+
+        if let Ok(hooks_path) = repo.config()?.get_path("core.hooksPath") {
+            let root = repo.workdir().unwrap_or(path);
+            return Ok(root.join(hooks_path));
+        }
+
+        Ok(repo.path().join("hooks"))
+    }
+
+    /// Commits reachable from HEAD that changed `file`, newest first.
+    pub fn file_history(
+        path: &Path,
+        file: &str,
+        limit: Option<usize>,
+    ) -> Result<Vec<FileRevision>> {
+        let repo =
+            Repository::open(path).map_err(|e| anyhow!("Failed to open Git repository: {}", e))?;
+
+        let mut revwalk = repo
+            .revwalk()
+            .map_err(|e| anyhow!("Failed to walk Git history: {}", e))?;
+        revwalk
+            .push_head()
+            .map_err(|e| anyhow!("Failed to read Git HEAD: {}", e))?;
+        revwalk
+            .set_sorting(Sort::TOPOLOGICAL | Sort::TIME)
+            .map_err(|e| anyhow!("Failed to walk Git history: {}", e))?;
+
+        let mut revisions = Vec::new();
+
+        for oid in revwalk {
+            if limit.is_some_and(|limit| revisions.len() >= limit) {
+                break;
+            }
+
+            let oid = oid.map_err(|e| anyhow!("Failed to walk Git history: {}", e))?;
+            let commit = repo
+                .find_commit(oid)
+                .map_err(|e| anyhow!("Failed to find Git commit: {}", e))?;
+
+            let tree = commit
+                .tree()
+                .map_err(|e| anyhow!("Failed to find Git tree: {}", e))?;
+            let blob = Self::blob_in_tree(&repo, &tree, file);
+
+            let parent_blob = match commit.parent(0) {
+                Ok(parent) => {
+                    let parent_tree = parent
+                        .tree()
+                        .map_err(|e| anyhow!("Failed to find Git tree: {}", e))?;
+                    Self::blob_in_tree(&repo, &parent_tree, file)
+                }
+                Err(_) => None,
+            };
+
+            if blob.as_ref().map(|(id, _)| *id) == parent_blob.as_ref().map(|(id, _)| *id) {
+                continue;
+            }
+
+            let author = commit.author();
+            revisions.push(FileRevision {
+                id: oid.to_string(),
+                author: format!(
+                    "{} <{}>",
+                    author.name().unwrap_or("unknown"),
+                    author.email().unwrap_or("")
+                ),
+                time: DateTime::from_timestamp(commit.time().seconds(), 0)
+                    .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                    .unwrap_or_default(),
+                message: commit.message().unwrap_or("").trim().to_string(),
+                content: blob.map(|(_, content)| content),
+                parent_content: parent_blob.map(|(_, content)| content),
+            });
+        }
+
+        Ok(revisions)
+    }
+
+    /// Contents of `file` at `rev` (any revision git understands), or `None` if it did not exist there.
+    pub fn file_at_revision(path: &Path, rev: &str, file: &str) -> Result<Option<String>> {
+        let repo =
+            Repository::open(path).map_err(|e| anyhow!("Failed to open Git repository: {}", e))?;
+
+        let tree = repo
+            .revparse_single(rev)
+            .and_then(|object| object.peel_to_tree())
+            .map_err(|e| anyhow!("Unknown revision '{}': {}", rev, e))?;
+
+        Ok(Self::blob_in_tree(&repo, &tree, file).map(|(_, content)| content))
+    }
+
+    /// The git config identity, falling back to a generic one when none is set.
+    fn signature(repo: &Repository) -> Result<Signature<'static>> {
+        match repo.signature() {
+            Ok(sig) => Ok(sig.to_owned()),
+            Err(_) => Signature::now("smolcase", "smolcase@localhost")
+                .map_err(|e| anyhow!("Failed to create Git signature: {}", e)),
+        }
+    }
+
+    fn current_branch(repo: &Repository) -> Result<String> {
+        let head = repo
+            .head()
+            .map_err(|e| anyhow!("Failed to read Git HEAD: {}", e))?;
+        if !head.is_branch() {
+            return Err(anyhow!("HEAD is detached. Check out a branch first."));
+        }
+
+        head.shorthand()
+            .map(|name| name.to_string())
+            .ok_or_else(|| anyhow!("Invalid branch name"))
+    }
+
+    /// Checks out `target` without touching local modifications, then points `branch` at it.
+    fn move_branch(repo: &Repository, branch: &str, target: Oid, message: &str) -> Result<()> {
+        let commit = repo
+            .find_object(target, None)
+            .map_err(|e| anyhow!("Failed to find Git commit: {}", e))?;
+        repo.checkout_tree(&commit, Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| anyhow!("Failed to update working tree: {}", e))?;
+
+        repo.find_reference(&format!("refs/heads/{}", branch))
+            .and_then(|mut reference| reference.set_target(target, message))
+            .map_err(|e| anyhow!("Failed to update branch '{}': {}", branch, e))?;
+
+        Ok(())
+    }
+
+    fn remote_callbacks(config: &git2::Config) -> RemoteCallbacks<'_> {
+        let attempts = Cell::new(0);
+        let mut callbacks = RemoteCallbacks::new();
+
+        callbacks.credentials(move |url, username, allowed| {
+            // libgit2 keeps asking while credentials fail
+            attempts.set(attempts.get() + 1);
+            if attempts.get() > 3 {
+                return Err(git2::Error::from_str("authentication failed"));
+            }
+
+            if allowed.contains(CredentialType::SSH_KEY) {
+                Cred::ssh_key_from_agent(username.unwrap_or("git"))
+            } else if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                Cred::credential_helper(config, url, username)
+            } else {
+                Cred::default()
+            }
+        });
+
+        callbacks
+    }
+
+    fn blob_text(repo: &Repository, id: Oid) -> Result<String> {
+        let blob = repo
+            .find_blob(id)
+            .map_err(|e| anyhow!("Failed to read Git blob: {}", e))?;
+        Ok(String::from_utf8_lossy(blob.content()).to_string())
+    }
+
+    fn blob_in_tree(repo: &Repository, tree: &Tree, file: &str) -> Option<(Oid, String)> {
+        let entry = tree.get_path(Path::new(file)).ok()?;
+        let blob = repo.find_blob(entry.id()).ok()?;
+        Some((
+            entry.id(),
+            String::from_utf8_lossy(blob.content()).to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remote_urls() {
+        assert!(GitManager::is_remote_url(
+            "https://github.com/org/secrets.git"
+        ));
+        assert!(GitManager::is_remote_url("ssh://git@host/org/secrets.git"));
+        assert!(GitManager::is_remote_url("file:///srv/secrets.git"));
+        assert!(GitManager::is_remote_url("git@github.com:org/secrets.git"));
+    }
+
+    #[test]
+    fn local_paths_are_not_remote() {
+        assert!(!GitManager::is_remote_url("../secrets"));
+        assert!(!GitManager::is_remote_url("/srv/secrets"));
+        assert!(!GitManager::is_remote_url("secrets"));
+        assert!(!GitManager::is_remote_url("./dir@2024:backup"));
+    }
 }
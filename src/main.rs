@@ -1,15 +1,25 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use colored::*;
+use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
 
+mod access;
+mod audit_log;
 mod commands;
 mod config;
 mod credential_manager;
 mod crypto;
 mod git;
+mod redact;
+mod s3_storage;
+mod secret_files;
+mod secret_scan;
+mod signing;
+mod storage;
+mod template;
 mod types;
 mod ui;
+mod vault_diff;
+mod vault_merge;
 
 use commands::*;
 
@@ -42,6 +52,8 @@ enum Commands {
     Configure,
     /// Clear cached credentials
     Logout,
+    /// Change your own user password
+    Passwd,
     /// Add a new secret or file
     Add {
         /// Secret key or file path
@@ -54,11 +66,20 @@ enum Commands {
         /// Groups to share with (comma-separated)
         #[arg(short, long)]
         groups: Option<String>,
+        /// Tags for grouping secrets (comma-separated)
+        #[arg(short, long)]
+        tags: Option<String>,
+        /// Don't auto-commit this change
+        #[arg(long)]
+        no_commit: bool,
     },
     /// Remove a secret
     Remove {
         /// Secret key to remove
         key: String,
+        /// Don't auto-commit this change
+        #[arg(long)]
+        no_commit: bool,
     },
     /// List accessible secrets
     List,
@@ -69,39 +90,56 @@ enum Commands {
     },
     /// Run command with secrets as environment variables
     Run {
-        /// Environment to use (optional)
-        #[arg(short, long)]
-        env: Option<String>,
+        #[command(flatten)]
+        options: RunOptions,
         /// Command to execute
         #[arg(last = true)]
         command: Vec<String>,
     },
     /// Apply template with secret substitution
     Apply {
-        /// Template file path
-        template: PathBuf,
-        /// Output file (stdout if not specified)
-        #[arg(short, long)]
-        output: Option<PathBuf>,
-        /// Environment to use (optional)
-        #[arg(short, long)]
-        env: Option<String>,
+        #[command(flatten)]
+        options: ApplyOptions,
+    },
+    /// Mask vault secret values in text read from stdin
+    Redact,
+    /// Join a project using an invite file
+    Join {
+        /// Invite file from 'smolcase user invite'
+        invite: PathBuf,
     },
     /// Set up user access for a repository
     Setup {
-        /// Repository URL or path
+        /// Repository URL (HTTPS, SSH, git@host:org/repo, file://) or path
         #[arg(short, long)]
         repo: Option<String>,
+        /// Directory to clone into (defaults to the repository name)
+        #[arg(short, long)]
+        dir: Option<PathBuf>,
+        /// Branch to check out after cloning
+        #[arg(short, long)]
+        branch: Option<String>,
     },
     /// Manage users (admin only)
     User {
         #[command(subcommand)]
         action: UserAction,
+        /// Don't auto-commit this change
+        #[arg(long, global = true)]
+        no_commit: bool,
     },
-    /// Manage groups (admin only)
+    /// Manage groups (admins and delegated group managers)
     Group {
         #[command(subcommand)]
         action: GroupAction,
+        /// Don't auto-commit this change
+        #[arg(long, global = true)]
+        no_commit: bool,
+    },
+    /// Manage admin settings (admin only)
+    Admin {
+        #[command(subcommand)]
+        action: AdminAction,
     },
     /// Export secrets as environment variables
     Export {
@@ -122,6 +160,76 @@ enum Commands {
         /// Input format (env, json, yaml)
         #[arg(short, long, default_value = "env")]
         format: String,
+        /// Don't auto-commit this change
+        #[arg(long)]
+        no_commit: bool,
+    },
+    /// Remove references to deleted users and groups (admin only)
+    Repair,
+    /// Inspect the audit log (admin only)
+    Audit {
+        #[command(subcommand)]
+        action: AuditAction,
+    },
+    /// Show decrypted vault changes from Git history (admin only)
+    Log {
+        /// Show at most this many commits
+        #[arg(short = 'n', long)]
+        limit: Option<usize>,
+        /// Include secret values in the output
+        #[arg(long)]
+        reveal: bool,
+    },
+    /// Show which secrets, users and groups changed between vault revisions (admin only)
+    Diff {
+        /// Revisions to compare (default: HEAD against the working tree)
+        #[arg(num_args = 0..=2)]
+        revs: Vec<String>,
+        /// Print a readable rendering of FILE, for use as a git textconv filter;
+        /// needs cached credentials and never shows values
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["revs", "reveal"])]
+        textconv: Option<PathBuf>,
+        /// Include secret values in the output
+        #[arg(long)]
+        reveal: bool,
+    },
+    /// Show or change project settings
+    Settings {
+        #[command(subcommand)]
+        action: Option<SettingsAction>,
+    },
+    /// Look for plaintext vault secrets and known credential formats
+    Scan {
+        /// File or directory to scan (default: current directory); .gitignore is respected
+        path: Option<PathBuf>,
+        /// Only scan what is staged for the next commit
+        #[arg(long, conflicts_with = "path")]
+        staged: bool,
+        /// Also scan every line ever committed to local branches
+        #[arg(long)]
+        history: bool,
+        /// Output format (text, json, sarif)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+        /// JSON file of accepted findings to leave out
+        #[arg(long, value_name = "FILE")]
+        baseline: Option<PathBuf>,
+        /// Accept all current findings by writing them to the baseline file
+        #[arg(long, requires = "baseline")]
+        update_baseline: bool,
+        /// Vault directory to take secret values from (default: $SMOLCASE_VAULT, then the current directory)
+        #[arg(long, value_name = "DIR")]
+        vault: Option<PathBuf>,
+    },
+    /// Manage Git hooks
+    Hooks {
+        #[command(subcommand)]
+        action: HooksAction,
+    },
+    /// Show or change where the vault is stored
+    Storage {
+        #[command(subcommand)]
+        action: Option<StorageAction>,
     },
     /// Sync with Git repository
     Sync,
@@ -129,6 +237,80 @@ enum Commands {
     Status,
 }
 
+#[derive(Args, Clone, Default)]
+struct RunOptions {
+    /// Only inject secrets for this environment (e.g. prod matches DB_URL_PROD)
+    #[arg(short, long)]
+    env: Option<String>,
+    /// Keep variables already set in the environment instead of replacing them
+    #[arg(long)]
+    no_override: bool,
+    /// Only inject these keys
+    #[arg(long, value_delimiter = ',', value_name = "KEY,...")]
+    only: Vec<String>,
+    /// Don't inject these keys
+    #[arg(long, value_delimiter = ',', value_name = "KEY,...")]
+    exclude: Vec<String>,
+    /// Prepend this to every variable name
+    #[arg(long)]
+    prefix: Option<String>,
+    /// Remove this from the start of variable names
+    #[arg(long)]
+    strip_prefix: Option<String>,
+    /// Append this to the names of variables holding file secret paths (e.g. _FILE)
+    #[arg(long)]
+    file_suffix: Option<String>,
+    /// Start the command with only the injected variables
+    #[arg(long)]
+    clean_env: bool,
+    /// Replace smolcase with the command instead of running it as a child (Unix)
+    #[arg(long, conflicts_with_all = ["watch", "redact"])]
+    exec: bool,
+    /// Restart the command when its secrets change in the vault
+    #[arg(long)]
+    watch: bool,
+    /// Signal that asks the command to stop before a restart [default: TERM]
+    #[arg(long, value_name = "SIGNAL")]
+    restart_signal: Option<String>,
+    /// Seconds to wait for the command to stop before killing it
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    grace_period: u64,
+    /// Mask secret values (and their base64 forms) in the command's output
+    #[arg(long)]
+    redact: bool,
+    /// List the variables that would be set without running anything
+    #[arg(long)]
+    dry_run: bool,
+}
+
+#[derive(Args)]
+struct ApplyOptions {
+    /// Template file path
+    #[arg(required_unless_present = "dir", conflicts_with = "dir")]
+    template: Option<PathBuf>,
+    /// Output file (stdout if not specified)
+    #[arg(short, long, conflicts_with = "dir")]
+    output: Option<PathBuf>,
+    /// Render every matching template under this directory
+    #[arg(long, requires = "out")]
+    dir: Option<PathBuf>,
+    /// Where --dir writes rendered files, mirroring the template tree
+    #[arg(long, requires = "dir")]
+    out: Option<PathBuf>,
+    /// Which files under --dir are templates; `**/` matches any depth
+    #[arg(long, default_value = "*.tmpl")]
+    pattern: String,
+    /// Environment to use (optional)
+    #[arg(short, long)]
+    env: Option<String>,
+    /// Fail instead of writing {{MISSING:NAME}} placeholders
+    #[arg(long)]
+    strict: bool,
+    /// Write nothing; fail if rendered output differs from the files on disk
+    #[arg(long)]
+    check: bool,
+}
+
 #[derive(Subcommand)]
 enum UserAction {
     /// Add a new user
@@ -136,13 +318,46 @@ enum UserAction {
         username: String,
         #[arg(short, long)]
         email: Option<String>,
+        /// Account expiry (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        expires: Option<String>,
+    },
+    /// Create a passphrase-protected invite file for a user
+    Invite {
+        username: String,
+        #[arg(short, long)]
+        email: Option<String>,
+        /// Invite file path (defaults to <username>.smol)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Hours until the invite expires
+        #[arg(long, default_value_t = 72)]
+        expires_in: u32,
+        /// Account expiry (YYYY-MM-DD or RFC 3339)
+        #[arg(long)]
+        expires: Option<String>,
     },
     /// Remove a user
-    Remove { username: String },
+    Remove {
+        username: String,
+        /// Also remove the user from groups and secret permissions
+        #[arg(long)]
+        force: bool,
+    },
     /// List all users
     List,
     /// Reset user password
     Reset { username: String },
+    /// Suspend a user's access
+    Suspend { username: String },
+    /// Restore a suspended user's access
+    Resume { username: String },
+    /// Set when a user's access expires
+    Expiry {
+        username: String,
+        /// Expiry date (YYYY-MM-DD or RFC 3339), or "never"
+        expires: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -154,13 +369,115 @@ enum GroupAction {
         description: Option<String>,
     },
     /// Delete a group
-    Delete { name: String },
+    Delete {
+        name: String,
+        /// Also remove the group from other groups and secret permissions
+        #[arg(long)]
+        force: bool,
+    },
     /// List all groups
     List,
     /// Add users to a group
     AddUser { group: String, users: Vec<String> },
     /// Remove users from a group
     RemoveUser { group: String, users: Vec<String> },
+    /// Show a group with its effective membership
+    Show { name: String },
+    /// Nest groups inside a group
+    AddGroup {
+        group: String,
+        subgroups: Vec<String>,
+    },
+    /// Remove nested groups from a group
+    RemoveGroup {
+        group: String,
+        subgroups: Vec<String>,
+    },
+    /// Let users manage membership of a group and its nested groups
+    Delegate { group: String, users: Vec<String> },
+    /// Revoke group management from users
+    Revoke { group: String, users: Vec<String> },
+}
+
+#[derive(Subcommand)]
+enum AdminAction {
+    /// Change the admin password
+    Passwd,
+    /// Sign the vault with the admin key, enabling signing if needed
+    Sign,
+}
+
+#[derive(Subcommand)]
+enum HooksAction {
+    /// Install a pre-commit hook that runs 'smolcase scan --staged'
+    Install {
+        /// Replace an existing pre-commit hook
+        #[arg(long)]
+        force: bool,
+        /// Vault directory the hook scans against, for hooks in application repositories
+        #[arg(long, value_name = "DIR")]
+        vault: Option<PathBuf>,
+    },
+    /// Remove the smolcase pre-commit hook
+    Uninstall,
+}
+
+#[derive(Subcommand)]
+enum StorageAction {
+    /// Show the configured storage backend
+    Show,
+    /// Move the vault to another backend (admin only)
+    Use {
+        #[command(subcommand)]
+        backend: StorageBackend,
+    },
+}
+
+#[derive(Subcommand)]
+enum StorageBackend {
+    /// Vault files in the project directory, shared through Git (default)
+    File,
+    /// A shared directory outside the repository
+    Directory { path: PathBuf },
+    /// An S3-compatible bucket (credentials from AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY)
+    S3 {
+        /// Endpoint URL, e.g. https://s3.eu-west-1.amazonaws.com or http://localhost:9000
+        #[arg(long)]
+        endpoint: String,
+        #[arg(long)]
+        bucket: String,
+        /// Key prefix for the vault objects
+        #[arg(long, default_value = "")]
+        prefix: String,
+        #[arg(long, default_value = "us-east-1")]
+        region: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SettingsAction {
+    /// Show all settings
+    Show,
+    /// Change a setting (admin only)
+    Set { key: String, value: String },
+}
+
+#[derive(Subcommand)]
+enum AuditAction {
+    /// Show audit entries
+    Log {
+        /// Only entries by this user
+        #[arg(short, long)]
+        user: Option<String>,
+        /// Only entries for this secret key
+        #[arg(short, long)]
+        key: Option<String>,
+        /// Only entries since a date (YYYY-MM-DD, RFC 3339, or e.g. 7d)
+        #[arg(short, long)]
+        since: Option<String>,
+    },
+    /// Check the hash chain for removed or edited entries
+    Verify,
 }
 
 #[tokio::main]
@@ -176,30 +493,67 @@ async fn main() -> Result<()> {
         Commands::Configure => configure::execute().await,
         Commands::Tutorial => tutorial::execute().await,
         Commands::Logout => logout::execute().await,
+        Commands::Passwd => passwd::execute().await,
         Commands::Add {
             key,
             value,
             users,
             groups,
-        } => add::execute(key, value, users, groups).await,
-        Commands::Remove { key } => remove::execute(key).await,
+            tags,
+            no_commit,
+        } => add::execute(key, value, users, groups, tags, no_commit).await,
+        Commands::Remove { key, no_commit } => remove::execute(key, no_commit).await,
         Commands::List => list::execute().await,
         Commands::Get { key } => get::execute(key).await,
-        Commands::Run { env, command } => run::execute(env, command).await,
-        Commands::Apply {
-            template,
-            output,
-            env,
-        } => apply::execute(template, output, env).await,
-        Commands::Setup { repo } => setup::execute(repo).await,
-        Commands::User { action } => user::execute(action).await,
-        Commands::Group { action } => group::execute(action).await,
+        Commands::Run { options, command } => run::execute(options, command).await,
+        Commands::Apply { options } => apply::execute(options).await,
+        Commands::Redact => commands::redact::execute().await,
+        Commands::Join { invite } => join::execute(invite).await,
+        Commands::Setup { repo, dir, branch } => setup::execute(repo, dir, branch).await,
+        Commands::User { action, no_commit } => user::execute(action, no_commit).await,
+        Commands::Group { action, no_commit } => group::execute(action, no_commit).await,
+        Commands::Admin { action } => admin::execute(action).await,
         Commands::Export {
             format,
             output,
             env,
         } => export::execute(format, output, env).await,
-        Commands::Import { file, format } => import::execute(file, format).await,
+        Commands::Import {
+            file,
+            format,
+            no_commit,
+        } => import::execute(file, format, no_commit).await,
+        Commands::Repair => repair::execute().await,
+        Commands::Audit { action } => audit::execute(action).await,
+        Commands::Log { limit, reveal } => log::execute(limit, reveal).await,
+        Commands::Diff {
+            revs,
+            textconv,
+            reveal,
+        } => diff::execute(revs, textconv, reveal).await,
+        Commands::Settings { action } => settings::execute(action).await,
+        Commands::Scan {
+            path,
+            staged,
+            history,
+            format,
+            baseline,
+            update_baseline,
+            vault,
+        } => {
+            scan::execute(
+                path,
+                staged,
+                history,
+                format,
+                baseline,
+                update_baseline,
+                vault,
+            )
+            .await
+        }
+        Commands::Hooks { action } => hooks::execute(action).await,
+        Commands::Storage { action } => commands::storage::execute(action).await,
         Commands::Sync => sync::execute().await,
         Commands::Status => status::execute().await,
     }
@@ -0,0 +1,537 @@
+use crate::config::CONFIG_FILE;
+use crate::git::FileRevision;
+use crate::storage::{Storage, StorageLock, VaultStorage};
+use anyhow::{Result, anyhow};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+const LOCK_OBJECT: &str = ".lock";
+
+/// Vault files as objects in an S3-compatible bucket (AWS S3, MinIO, ...).
+///
+/// Requests use path-style URLs and SigV4 signing. Locking relies on
+/// conditional writes (`If-None-Match: *`), which S3 and MinIO both support,
+/// and locks are only deleted by ETag (`If-Match`).
+pub struct S3Storage {
+    endpoint: String,
+    bucket: String,
+    prefix: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+impl S3Storage {
+    pub fn new(endpoint: &str, bucket: &str, prefix: &str, region: &str) -> Result<Self> {
+        let access_key = std::env::var("AWS_ACCESS_KEY_ID")
+            .map_err(|_| anyhow!("Set AWS_ACCESS_KEY_ID to use the S3 storage backend"))?;
+        let secret_key = std::env::var("AWS_SECRET_ACCESS_KEY")
+            .map_err(|_| anyhow!("Set AWS_SECRET_ACCESS_KEY to use the S3 storage backend"))?;
+
+        let mut prefix = prefix.trim_matches('/').to_string();
+        if !prefix.is_empty() {
+            prefix.push('/');
+        }
+
+        Ok(Self {
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            bucket: bucket.to_string(),
+            prefix,
+            region: region.to_string(),
+            access_key,
+            secret_key,
+            session_token: std::env::var("AWS_SESSION_TOKEN").ok(),
+        })
+    }
+
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        match self.request("GET", name, &[], b"", &[])? {
+            (200, body) => Ok(Some(body)),
+            (404, _) => Ok(None),
+            (status, body) => Err(self.error("read", name, status, &body)),
+        }
+    }
+
+    fn put(&self, name: &str, content: &str) -> Result<()> {
+        match self.request("PUT", name, &[], content.as_bytes(), &[])? {
+            (200, _) => Ok(()),
+            (status, body) => Err(self.error("write", name, status, &body)),
+        }
+    }
+
+    /// Object names under `prefix` (relative to the vault prefix), sorted.
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let key_pattern = Regex::new(r"<Key>([^<]*)</Key>").expect("valid regex");
+        let token_pattern = Regex::new(r"<NextContinuationToken>([^<]*)</NextContinuationToken>")
+            .expect("valid regex");
+        let full_prefix = format!("{}{}", self.prefix, prefix);
+
+        let mut names = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("list-type".to_string(), "2".to_string()),
+                ("prefix".to_string(), full_prefix.clone()),
+            ];
+            if let Some(token) = &token {
+                query.push(("continuation-token".to_string(), xml_unescape(token)));
+            }
+
+            let body = match self.request("GET", "", &query, b"", &[])? {
+                (200, body) => body,
+                (status, body) => return Err(self.error("list", prefix, status, &body)),
+            };
+
+            names.extend(key_pattern.captures_iter(&body).filter_map(|c| {
+                xml_unescape(&c[1])
+                    .strip_prefix(&self.prefix)
+                    .map(str::to_string)
+            }));
+
+            match token_pattern.captures(&body) {
+                Some(next) if body.contains("<IsTruncated>true</IsTruncated>") => {
+                    token = Some(next[1].to_string());
+                }
+                _ => break,
+            }
+        }
+
+        names.sort();
+        Ok(names)
+    }
+
+    /// Sends a SigV4-signed request for object `name` (or the bucket when
+    /// empty) and returns the HTTP status with the response body.
+    fn request(
+        &self,
+        method: &str,
+        name: &str,
+        query: &[(String, String)],
+        body: &[u8],
+        extra_headers: &[(&str, &str)],
+    ) -> Result<(u16, String)> {
+        self.request_with_etag(method, name, query, body, extra_headers)
+            .map(|(status, body, _)| (status, body))
+    }
+
+    /// Like `request`, also returning the object's ETag when the response has one.
+    fn request_with_etag(
+        &self,
+        method: &str,
+        name: &str,
+        query: &[(String, String)],
+        body: &[u8],
+        extra_headers: &[(&str, &str)],
+    ) -> Result<(u16, String, Option<String>)> {
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex(&Sha256::digest(body));
+
+        let path = if name.is_empty() {
+            format!("/{}", uri_encode(&self.bucket, false))
+        } else {
+            format!(
+                "/{}/{}",
+                uri_encode(&self.bucket, false),
+                uri_encode(&format!("{}{}", self.prefix, name), false)
+            )
+        };
+
+        let mut query: Vec<(String, String)> = query
+            .iter()
+            .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+            .collect();
+        query.sort();
+        let query_string = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let host = self
+            .endpoint
+            .split("://")
+            .nth(1)
+            .unwrap_or(&self.endpoint)
+            .split('/')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+
+        let mut headers: Vec<(String, String)> = vec![
+            ("host".to_string(), host),
+            ("x-amz-content-sha256".to_string(), payload_hash.clone()),
+            ("x-amz-date".to_string(), amz_date.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token".to_string(), token.clone()));
+        }
+        for (name, value) in extra_headers {
+            headers.push((name.to_lowercase(), value.to_string()));
+        }
+        headers.sort();
+
+        let signature = sign(
+            &self.secret_key,
+            &self.region,
+            &amz_date,
+            &Canonical {
+                method,
+                path: &path,
+                query: &query_string,
+                headers: &headers,
+                payload_hash: &payload_hash,
+            },
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+
+        let mut url = format!("{}{}", self.endpoint, path);
+        if !query_string.is_empty() {
+            url.push('?');
+            url.push_str(&query_string);
+        }
+
+        let mut request = ureq::request(method, &url).set(
+            "Authorization",
+            &format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key, scope, signature.signed_headers, signature.value
+            ),
+        );
+        for (name, value) in &headers {
+            if name != "host" {
+                request = request.set(name, value);
+            }
+        }
+
+        let response = match request.send_bytes(body) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(anyhow!("Cannot reach {}: {}", self.endpoint, e)),
+        };
+
+        let status = response.status();
+        let etag = response.header("ETag").map(str::to_string);
+        Ok((status, response.into_string()?, etag))
+    }
+
+    /// Deletes the lock object if it is still the one `owner` wrote. With an
+    /// ETag the check is done by the server (`If-Match`); without one it falls
+    /// back to comparing the content first.
+    fn delete_lock(&self, owner: &str, etag: Option<&str>) {
+        match etag {
+            Some(etag) => {
+                let _ = self.request("DELETE", LOCK_OBJECT, &[], b"", &[("If-Match", etag)]);
+            }
+            None => {
+                if self.get(LOCK_OBJECT).ok().flatten().as_deref() == Some(owner) {
+                    let _ = self.request("DELETE", LOCK_OBJECT, &[], b"", &[]);
+                }
+            }
+        }
+    }
+
+    fn error(&self, action: &str, name: &str, status: u16, body: &str) -> anyhow::Error {
+        let message = Regex::new(r"<Message>([^<]*)</Message>")
+            .expect("valid regex")
+            .captures(body)
+            .map(|c| format!(": {}", xml_unescape(&c[1])))
+            .unwrap_or_default();
+
+        anyhow!(
+            "Failed to {} s3://{}/{}{}: HTTP {}{}",
+            action,
+            self.bucket,
+            self.prefix,
+            name,
+            status,
+            message
+        )
+    }
+}
+
+impl VaultStorage for S3Storage {
+    fn load(&self, file: &str) -> Result<Option<String>> {
+        self.get(file)
+    }
+
+    fn save(&self, file: &str, content: &str) -> Result<()> {
+        if file == CONFIG_FILE {
+            self.put(&Storage::revision_name(content), content)?;
+        }
+        self.put(file, content)
+    }
+
+    fn lock(&self) -> Result<StorageLock<'_>> {
+        let mut held = None;
+        Storage::acquire(
+            || {
+                let owner = Storage::lock_owner();
+                match self.request_with_etag(
+                    "PUT",
+                    LOCK_OBJECT,
+                    &[],
+                    owner.as_bytes(),
+                    &[("If-None-Match", "*")],
+                )? {
+                    (200, _, etag) => {
+                        held = Some((owner, etag));
+                        Ok(true)
+                    }
+                    (409 | 412, _, _) => {
+                        if let (200, stale, Some(etag)) =
+                            self.request_with_etag("GET", LOCK_OBJECT, &[], b"", &[])?
+                            && Storage::is_stale(&stale)
+                        {
+                            // Only the lock we judged stale, not one taken since
+                            self.delete_lock(&stale, Some(&etag));
+                        }
+                        Ok(false)
+                    }
+                    (status, body, _) => Err(self.error("lock", LOCK_OBJECT, status, &body)),
+                }
+            },
+            &format!("s3://{}/{}{}", self.bucket, self.prefix, LOCK_OBJECT),
+        )?;
+
+        let (owner, etag) = held.expect("acquire only succeeds once the lock is held");
+        Ok(StorageLock::new(move || {
+            self.delete_lock(&owner, etag.as_deref());
+        }))
+    }
+
+    fn revisions(&self, limit: Option<usize>) -> Result<Vec<FileRevision>> {
+        let names = self.list("revisions/")?;
+
+        // One extra so the oldest shown revision still has its parent
+        let skip = limit.map_or(0, |limit| names.len().saturating_sub(limit + 1));
+        let mut saved = Vec::new();
+        for name in names.into_iter().skip(skip) {
+            if let Some(content) = self.get(&name)? {
+                saved.push((name, content));
+            }
+        }
+
+        Ok(Storage::history(saved, limit, &self.describe()))
+    }
+
+    fn describe(&self) -> String {
+        format!("s3 {}/{}/{}", self.endpoint, self.bucket, self.prefix)
+    }
+}
+
+/// The parts of a request that SigV4 signs. Headers are lowercased and sorted.
+struct Canonical<'a> {
+    method: &'a str,
+    path: &'a str,
+    query: &'a str,
+    headers: &'a [(String, String)],
+    payload_hash: &'a str,
+}
+
+struct Signature {
+    signed_headers: String,
+    value: String,
+}
+
+impl Canonical<'_> {
+    fn signed_headers(&self) -> String {
+        self.headers
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+
+    fn request(&self) -> String {
+        let headers: String = self
+            .headers
+            .iter()
+            .map(|(k, v)| format!("{}:{}\n", k, v.trim()))
+            .collect();
+
+        format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            self.method,
+            self.path,
+            self.query,
+            headers,
+            self.signed_headers(),
+            self.payload_hash
+        )
+    }
+}
+
+fn sign(secret_key: &str, region: &str, amz_date: &str, request: &Canonical) -> Signature {
+    let date = &amz_date[..8];
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}/{}/s3/aws4_request\n{}",
+        amz_date,
+        date,
+        region,
+        hex(&Sha256::digest(request.request().as_bytes()))
+    );
+
+    let mut key = hmac(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes());
+    for part in [region, "s3", "aws4_request"] {
+        key = hmac(&key, part.as_bytes());
+    }
+
+    Signature {
+        signed_headers: request.signed_headers(),
+        value: hex(&hmac(&key, string_to_sign.as_bytes())),
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// SigV4 URI encoding; `/` is kept in paths and encoded in query values.
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b'/' if !encode_slash => "/".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Examples from the AWS S3 documentation, "Signature Calculations for the
+    // Authorization Header: Transferring Payload in a Single Chunk"
+    const SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY";
+    const AMZ_DATE: &str = "20130524T000000Z";
+    const EMPTY_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn signs_get_object_example() {
+        let headers = headers(&[
+            ("host", "examplebucket.s3.amazonaws.com"),
+            ("range", "bytes=0-9"),
+            ("x-amz-content-sha256", EMPTY_HASH),
+            ("x-amz-date", AMZ_DATE),
+        ]);
+        let request = Canonical {
+            method: "GET",
+            path: "/test.txt",
+            query: "",
+            headers: &headers,
+            payload_hash: EMPTY_HASH,
+        };
+
+        assert_eq!(
+            request.request(),
+            format!(
+                "GET\n/test.txt\n\nhost:examplebucket.s3.amazonaws.com\nrange:bytes=0-9\n\
+                 x-amz-content-sha256:{EMPTY_HASH}\nx-amz-date:{AMZ_DATE}\n\n\
+                 host;range;x-amz-content-sha256;x-amz-date\n{EMPTY_HASH}"
+            )
+        );
+
+        let signature = sign(SECRET_KEY, "us-east-1", AMZ_DATE, &request);
+        assert_eq!(
+            signature.signed_headers,
+            "host;range;x-amz-content-sha256;x-amz-date"
+        );
+        assert_eq!(
+            signature.value,
+            "f0e8bdb87c964420e857bd35b5d6ed310bd44f0170aba48dd91039c6036bdb41"
+        );
+    }
+
+    #[test]
+    fn signs_put_object_example() {
+        let body = b"Welcome to Amazon S3.";
+        let payload_hash = hex(&Sha256::digest(body));
+        assert_eq!(
+            payload_hash,
+            "44ce7dd67c959e0d3524ffac1771dfbba87d2b6b4b4e99e42034a8b803f8b072"
+        );
+
+        let headers = headers(&[
+            ("date", "Fri, 24 May 2013 00:00:00 GMT"),
+            ("host", "examplebucket.s3.amazonaws.com"),
+            ("x-amz-content-sha256", &payload_hash),
+            ("x-amz-date", AMZ_DATE),
+            ("x-amz-storage-class", "REDUCED_REDUNDANCY"),
+        ]);
+        let path = format!("/{}", uri_encode("test$file.text", false));
+        assert_eq!(path, "/test%24file.text");
+
+        let request = Canonical {
+            method: "PUT",
+            path: &path,
+            query: "",
+            headers: &headers,
+            payload_hash: &payload_hash,
+        };
+        assert_eq!(
+            sign(SECRET_KEY, "us-east-1", AMZ_DATE, &request).value,
+            "98ad721746da40c64f1a55b78f14c238d841ea1380cd77a1b5971af0ece108bd"
+        );
+    }
+
+    #[test]
+    fn signs_list_objects_example() {
+        let headers = headers(&[
+            ("host", "examplebucket.s3.amazonaws.com"),
+            ("x-amz-content-sha256", EMPTY_HASH),
+            ("x-amz-date", AMZ_DATE),
+        ]);
+        let request = Canonical {
+            method: "GET",
+            path: "/",
+            query: "max-keys=2&prefix=J",
+            headers: &headers,
+            payload_hash: EMPTY_HASH,
+        };
+        assert_eq!(
+            sign(SECRET_KEY, "us-east-1", AMZ_DATE, &request).value,
+            "34b48302e7b5fa45bde8084f4b7868a86f0a534bc59db6670ed5711ef69dc6f7"
+        );
+    }
+
+    #[test]
+    fn encodes_query_values_but_keeps_path_slashes() {
+        assert_eq!(
+            uri_encode("vault/revisions/a b", false),
+            "vault/revisions/a%20b"
+        );
+        assert_eq!(uri_encode("vault/revisions/", true), "vault%2Frevisions%2F");
+        assert_eq!(uri_encode("~-_.", true), "~-_.");
+    }
+}
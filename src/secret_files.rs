@@ -0,0 +1,136 @@
+use crate::types::SecretValue;
+use anyhow::{Result, anyhow};
+use base64::Engine;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+/// File secrets written out for a child process. The directory and
+/// everything in it is removed when this is dropped.
+#[derive(Default)]
+pub struct SecretFiles {
+    dir: Option<TempDir>,
+}
+
+impl SecretFiles {
+    /// Writes `content` to a file named after `key`, readable only by us. Each
+    /// file gets its own subdirectory named after the environment variable that
+    /// points to it, so `a/tls.key` and `b/tls.key` don't collide.
+    pub fn write(&mut self, env_name: &str, key: &str, content: &[u8]) -> Result<PathBuf> {
+        if self.dir.is_none() {
+            let dir = tempfile::Builder::new()
+                .prefix("smolcase-")
+                .tempdir_in(Self::base_dir())
+                .map_err(|e| anyhow!("Failed to create a directory for file secrets: {}", e))?;
+
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o700))?;
+            }
+            self.dir = Some(dir);
+        }
+        let dir = self.dir.as_ref().expect("created above");
+
+        // Vault keys for files are file names; never let one escape the directory
+        let name = safe_name(key.rsplit(['/', '\\']).next().unwrap_or(key));
+        let subdir = dir
+            .path()
+            .join(safe_name(&env_name.replace(['/', '\\'], "_")));
+        fs::create_dir(&subdir)
+            .map_err(|e| anyhow!("Failed to write file secret {}: {}", key, e))?;
+        let path = subdir.join(name);
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        let mut file = options
+            .open(&path)
+            .map_err(|e| anyhow!("Failed to write file secret {}: {}", key, e))?;
+        file.write_all(content)
+            .map_err(|e| anyhow!("Failed to write file secret {}: {}", key, e))?;
+
+        Ok(path)
+    }
+
+    /// Memory-backed storage when the system has it, so secrets never touch disk.
+    fn base_dir() -> PathBuf {
+        let candidates = [
+            std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from),
+            Some(PathBuf::from("/dev/shm")),
+        ];
+
+        candidates
+            .into_iter()
+            .flatten()
+            .find(|dir| fs::metadata(dir).is_ok_and(|m| m.is_dir() && !m.permissions().readonly()))
+            .unwrap_or_else(std::env::temp_dir)
+    }
+}
+
+fn safe_name(name: &str) -> &str {
+    match name {
+        "" | "." | ".." => "secret",
+        name => name,
+    }
+}
+
+/// Environment variable name for a vault key such as `server.key`.
+pub fn env_name(key: &str) -> String {
+    let mut name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// The content of a file secret; older vaults only kept it base64-encoded in `value`.
+pub fn file_content(secret: &SecretValue) -> Result<Vec<u8>> {
+    match &secret.file_content {
+        Some(content) => Ok(content.clone()),
+        None => base64::engine::general_purpose::STANDARD
+            .decode(&secret.value)
+            .map_err(|_| anyhow!("File secret {} has no content", secret.key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_file_name_under_different_keys() {
+        let mut files = SecretFiles::default();
+        let a = files.write("A_TLS_KEY", "a/tls.key", b"first").unwrap();
+        let b = files.write("B_TLS_KEY", "b/tls.key", b"second").unwrap();
+
+        assert_ne!(a, b);
+        assert!(a.ends_with("A_TLS_KEY/tls.key"));
+        assert_eq!(fs::read(&a).unwrap(), b"first");
+        assert_eq!(fs::read(&b).unwrap(), b"second");
+    }
+
+    #[test]
+    fn keys_cannot_escape_the_directory() {
+        let mut files = SecretFiles::default();
+        let path = files.write("../X", "..", b"content").unwrap();
+
+        assert!(path.ends_with(".._X/secret"));
+    }
+}
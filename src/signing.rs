@@ -0,0 +1,658 @@
+use crate::crypto::CryptoManager;
+use crate::types::{
+    EncryptedData, Group, MembershipSignature, PrivateConfig, ProjectSettings, Secret,
+    SmolcaseConfig, User,
+};
+use anyhow::{Result, anyhow};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand::rngs::OsRng;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Everything only an admin may change. Password hashes, invite tokens and the
+/// members of delegated groups are left out so users and group managers can
+/// still update them without the admin key; delegated members carry their own
+/// `SignedMembership` signature instead.
+#[derive(Serialize)]
+struct SignedContent<'a> {
+    project_name: &'a str,
+    admin_key_hash: &'a str,
+    master_key_hash: &'a str,
+    admin_public_key: &'a str,
+    // Vaults signed before revisions existed still verify
+    #[serde(skip_serializing_if = "is_zero")]
+    revision: u64,
+    settings: &'a ProjectSettings,
+    secrets: BTreeMap<&'a String, &'a Secret>,
+    encrypted_secrets: &'a EncryptedData,
+    users: BTreeMap<&'a String, SignedUser<'a>>,
+    groups: BTreeMap<&'a String, SignedGroup<'a>>,
+}
+
+#[derive(Serialize)]
+struct SignedUser<'a> {
+    id: &'a Uuid,
+    email: &'a Option<String>,
+    created_at: &'a str,
+    is_admin: bool,
+    expires_at: &'a Option<String>,
+    disabled: bool,
+    // Users created before membership signing still verify
+    #[serde(skip_serializing_if = "Option::is_none")]
+    public_key: &'a Option<String>,
+}
+
+#[derive(Serialize)]
+struct SignedGroup<'a> {
+    id: &'a Uuid,
+    description: &'a Option<String>,
+    members: Option<&'a Vec<String>>,
+    subgroups: &'a Vec<String>,
+    managers: &'a Vec<String>,
+    created_at: &'a str,
+}
+
+/// The members of a delegated group, signed by one of its managers or the admin.
+#[derive(Serialize)]
+struct SignedMembership<'a> {
+    vault: &'a str,
+    group: &'a Uuid,
+    name: &'a str,
+    members: &'a Vec<String>,
+    // Signatures made before counters existed still verify
+    #[serde(skip_serializing_if = "is_zero")]
+    counter: u64,
+}
+
+/// Ed25519 signatures over the admin-controlled part of the vault.
+///
+/// The public key lives in `SmolcaseConfig`; the private key sits next to it,
+/// encrypted with the admin password, so holding the master key alone is not
+/// enough to produce a vault that verifies. Each user has a key pair of their
+/// own, the public half signed by the admin and the private half encrypted
+/// with their password, so group managers can sign membership changes.
+pub struct VaultSigner;
+
+impl VaultSigner {
+    /// Creates a signing key for the vault and signs it.
+    pub fn enable(
+        public_config: &mut SmolcaseConfig,
+        private_config: &mut PrivateConfig,
+        admin_password: &str,
+    ) -> Result<()> {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        public_config.admin_public_key =
+            Some(BASE64.encode(signing_key.verifying_key().as_bytes()));
+        public_config.admin_signing_key = Some(CryptoManager::encrypt_data_with_salt(
+            signing_key.as_bytes(),
+            admin_password,
+        )?);
+
+        Self::sign(public_config, private_config, admin_password)
+    }
+
+    /// Signs the vault, and endorses the current members of every delegated
+    /// group that no manager has validly signed.
+    pub fn sign(
+        public_config: &mut SmolcaseConfig,
+        private_config: &mut PrivateConfig,
+        admin_password: &str,
+    ) -> Result<()> {
+        let signing_key = Self::signing_key(public_config, admin_password)?;
+        let payload = Self::payload(public_config, private_config)?;
+        public_config.signature = Some(BASE64.encode(signing_key.sign(&payload).to_bytes()));
+
+        for name in Self::unsigned_groups(public_config, private_config) {
+            let group = &private_config.groups[&name];
+            let counter = Self::next_counter(group);
+            let payload = Self::membership_payload(public_config, group, counter)?;
+            let signature = BASE64.encode(signing_key.sign(&payload).to_bytes());
+            if let Some(group) = private_config.groups.get_mut(&name) {
+                group.membership_signature = Some(MembershipSignature {
+                    manager: None,
+                    signature,
+                    counter,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Signs the members of `group` with the key of `manager`, who must manage it.
+    pub fn sign_membership(
+        public_config: &SmolcaseConfig,
+        private_config: &mut PrivateConfig,
+        group: &str,
+        manager: &str,
+        password: &str,
+    ) -> Result<()> {
+        if !private_config.can_manage_group(manager, group) {
+            return Err(anyhow!(
+                "User '{}' is not a manager of group '{}'",
+                manager,
+                group
+            ));
+        }
+
+        let user = private_config
+            .users
+            .get(manager)
+            .ok_or_else(|| anyhow!("User '{}' not found", manager))?;
+        let signing_key = Self::user_signing_key(user, password)?;
+        let group_obj = private_config
+            .groups
+            .get(group)
+            .ok_or_else(|| anyhow!("Group '{}' not found", group))?;
+
+        let counter = Self::next_counter(group_obj);
+        let payload = Self::membership_payload(public_config, group_obj, counter)?;
+        let signature = BASE64.encode(signing_key.sign(&payload).to_bytes());
+        if let Some(group_obj) = private_config.groups.get_mut(group) {
+            group_obj.membership_signature = Some(MembershipSignature {
+                manager: Some(manager.to_string()),
+                signature,
+                counter,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Gives `user` a new key pair, the private half encrypted with `password`.
+    /// The public half is admin-signed content.
+    pub fn new_user_key(user: &mut User, password: &str) -> Result<()> {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        user.public_key = Some(BASE64.encode(signing_key.verifying_key().as_bytes()));
+        user.signing_key = Some(CryptoManager::encrypt_data_with_salt(
+            signing_key.as_bytes(),
+            password,
+        )?);
+
+        Ok(())
+    }
+
+    /// Like `new_user_key` for a user who hasn't chosen a password yet: the
+    /// private half is returned to travel in their invite.
+    pub fn new_invite_key(user: &mut User) -> String {
+        let signing_key = SigningKey::generate(&mut OsRng);
+        user.public_key = Some(BASE64.encode(signing_key.verifying_key().as_bytes()));
+        user.signing_key = None;
+
+        BASE64.encode(signing_key.as_bytes())
+    }
+
+    /// Stores the key from an invite, encrypted with the password the user chose.
+    pub fn adopt_invite_key(user: &mut User, key: &str, password: &str) -> Result<()> {
+        let key_bytes: [u8; 32] = BASE64
+            .decode(key)
+            .ok()
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| anyhow!("Invalid signing key in invite"))?;
+        let signing_key = SigningKey::from_bytes(&key_bytes);
+
+        if Some(BASE64.encode(signing_key.verifying_key().as_bytes())) != user.public_key {
+            return Err(anyhow!(
+                "The invite's signing key does not match the vault. Ask an admin for a new invite."
+            ));
+        }
+        user.signing_key = Some(CryptoManager::encrypt_data_with_salt(
+            signing_key.as_bytes(),
+            password,
+        )?);
+
+        Ok(())
+    }
+
+    /// Re-encrypts a user's key after they change their password.
+    pub fn rekey_user(user: &mut User, old_password: &str, new_password: &str) -> Result<()> {
+        if user.signing_key.is_none() {
+            return Ok(());
+        }
+
+        let signing_key = Self::user_signing_key(user, old_password)?;
+        user.signing_key = Some(CryptoManager::encrypt_data_with_salt(
+            signing_key.as_bytes(),
+            new_password,
+        )?);
+
+        Ok(())
+    }
+
+    /// Re-encrypts the signing key after an admin password change.
+    pub fn rekey(
+        public_config: &mut SmolcaseConfig,
+        old_password: &str,
+        new_password: &str,
+    ) -> Result<()> {
+        if public_config.admin_signing_key.is_none() {
+            return Ok(());
+        }
+
+        let signing_key = Self::signing_key(public_config, old_password)?;
+        public_config.admin_signing_key = Some(CryptoManager::encrypt_data_with_salt(
+            signing_key.as_bytes(),
+            new_password,
+        )?);
+
+        Ok(())
+    }
+
+    /// Succeeds for unsigned vaults; use `is_signed` to tell them apart.
+    pub fn verify(public_config: &SmolcaseConfig, private_config: &PrivateConfig) -> Result<()> {
+        Self::verify_content(public_config, private_config)?;
+
+        let unsigned = Self::unsigned_groups(public_config, private_config);
+        if !unsigned.is_empty() {
+            return Err(anyhow!(
+                "Vault signature check failed: members of group(s) {} were changed without \
+                 a manager's or admin's signature. Restore .smolcase.yml from Git or ask an admin \
+                 to review and re-sign it with 'smolcase admin sign'.",
+                unsigned.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks the admin signature alone, leaving out delegated group members.
+    pub fn verify_content(
+        public_config: &SmolcaseConfig,
+        private_config: &PrivateConfig,
+    ) -> Result<()> {
+        let Some(public_key) = &public_config.admin_public_key else {
+            return Ok(());
+        };
+
+        let tampered = || {
+            anyhow!(
+                "Vault signature check failed: .smolcase.yml was changed without the admin signing key. \
+                 Restore it from Git or ask an admin to review and re-sign it with 'smolcase admin sign'."
+            )
+        };
+
+        let verifying_key = verifying_key(public_key)
+            .ok_or_else(|| anyhow!("Invalid admin public key in vault"))?;
+
+        let signature_bytes: [u8; 64] = public_config
+            .signature
+            .as_ref()
+            .and_then(|s| BASE64.decode(s).ok())
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(tampered)?;
+
+        let payload = Self::payload(public_config, private_config)?;
+        verifying_key
+            .verify(&payload, &Signature::from_bytes(&signature_bytes))
+            .map_err(|_| tampered())
+    }
+
+    pub fn is_signed(public_config: &SmolcaseConfig) -> bool {
+        public_config.admin_public_key.is_some()
+    }
+
+    /// Delegated groups whose members lack a valid signature from a current
+    /// manager or the admin. Always empty for unsigned vaults.
+    pub fn unsigned_groups(
+        public_config: &SmolcaseConfig,
+        private_config: &PrivateConfig,
+    ) -> Vec<String> {
+        let Some(admin_key) = &public_config.admin_public_key else {
+            return Vec::new();
+        };
+
+        let mut unsigned: Vec<String> = private_config
+            .groups
+            .values()
+            .filter(|group| private_config.is_delegated(&group.name))
+            .filter(|group| {
+                let Some(signed) = &group.membership_signature else {
+                    return !group.members.is_empty();
+                };
+
+                let key = match &signed.manager {
+                    None => Some(admin_key),
+                    Some(manager) if private_config.can_manage_group(manager, &group.name) => {
+                        private_config
+                            .users
+                            .get(manager)
+                            .and_then(|u| u.public_key.as_ref())
+                    }
+                    Some(_) => None,
+                };
+                let valid = key.and_then(|k| verifying_key(k)).is_some_and(|key| {
+                    let signature: Option<[u8; 64]> = BASE64
+                        .decode(&signed.signature)
+                        .ok()
+                        .and_then(|b| b.try_into().ok());
+                    match (
+                        signature,
+                        Self::membership_payload(public_config, group, signed.counter),
+                    ) {
+                        (Some(signature), Ok(payload)) => key
+                            .verify(&payload, &Signature::from_bytes(&signature))
+                            .is_ok(),
+                        _ => false,
+                    }
+                });
+                !valid
+            })
+            .map(|group| group.name.clone())
+            .collect();
+
+        unsigned.sort();
+        unsigned
+    }
+
+    fn user_signing_key(user: &User, password: &str) -> Result<SigningKey> {
+        let encrypted = user.signing_key.as_ref().ok_or_else(|| {
+            anyhow!(
+                "User '{}' has no signing key yet. Ask an admin to run 'smolcase user reset {}' or send a new invite.",
+                user.username,
+                user.username
+            )
+        })?;
+
+        let key_bytes: [u8; 32] = CryptoManager::decrypt_data_with_salt(encrypted, password)
+            .map_err(|_| anyhow!("Invalid password for signing key"))?
+            .try_into()
+            .map_err(|_| anyhow!("Invalid signing key for user '{}'", user.username))?;
+        let signing_key = SigningKey::from_bytes(&key_bytes);
+
+        if Some(BASE64.encode(signing_key.verifying_key().as_bytes())) != user.public_key {
+            return Err(anyhow!(
+                "Signing key of user '{}' does not match their public key",
+                user.username
+            ));
+        }
+
+        Ok(signing_key)
+    }
+
+    /// The counter a delegated group's members were last signed with; 0 if never.
+    pub fn membership_counter(group: &Group) -> u64 {
+        group.membership_signature.as_ref().map_or(0, |s| s.counter)
+    }
+
+    fn next_counter(group: &Group) -> u64 {
+        Self::membership_counter(group).saturating_add(1)
+    }
+
+    fn membership_payload(
+        public_config: &SmolcaseConfig,
+        group: &Group,
+        counter: u64,
+    ) -> Result<Vec<u8>> {
+        let content = SignedMembership {
+            vault: public_config.admin_public_key.as_deref().unwrap_or(""),
+            group: &group.id,
+            name: &group.name,
+            members: &group.members,
+            counter,
+        };
+
+        Ok(serde_json::to_vec(&content)?)
+    }
+
+    fn signing_key(public_config: &SmolcaseConfig, admin_password: &str) -> Result<SigningKey> {
+        let encrypted = public_config
+            .admin_signing_key
+            .as_ref()
+            .ok_or_else(|| anyhow!("Vault signing is not enabled. Run 'smolcase admin sign'."))?;
+
+        let key_bytes: [u8; 32] = CryptoManager::decrypt_data_with_salt(encrypted, admin_password)
+            .map_err(|_| anyhow!("Only an admin can sign this change (invalid admin password)"))?
+            .try_into()
+            .map_err(|_| anyhow!("Invalid admin signing key in vault"))?;
+        let signing_key = SigningKey::from_bytes(&key_bytes);
+
+        if Some(BASE64.encode(signing_key.verifying_key().as_bytes()))
+            != public_config.admin_public_key
+        {
+            return Err(anyhow!(
+                "Admin signing key does not match the vault's public key"
+            ));
+        }
+
+        Ok(signing_key)
+    }
+
+    fn payload(public_config: &SmolcaseConfig, private_config: &PrivateConfig) -> Result<Vec<u8>> {
+        let content = SignedContent {
+            project_name: &public_config.project_name,
+            admin_key_hash: &public_config.admin_key_hash,
+            master_key_hash: &public_config.master_key_hash,
+            admin_public_key: public_config.admin_public_key.as_deref().unwrap_or(""),
+            revision: public_config.revision,
+            settings: &public_config.settings,
+            secrets: private_config.secrets.iter().collect(),
+            encrypted_secrets: &private_config.encrypted_secrets,
+            users: private_config
+                .users
+                .iter()
+                .map(|(name, user)| {
+                    (
+                        name,
+                        SignedUser {
+                            id: &user.id,
+                            email: &user.email,
+                            created_at: &user.created_at,
+                            is_admin: user.is_admin,
+                            expires_at: &user.expires_at,
+                            disabled: user.disabled,
+                            public_key: &user.public_key,
+                        },
+                    )
+                })
+                .collect(),
+            groups: private_config
+                .groups
+                .iter()
+                .map(|(name, group)| (name, Self::signed_group(private_config, group)))
+                .collect(),
+        };
+
+        Ok(serde_json::to_vec(&content)?)
+    }
+
+    fn signed_group<'a>(private_config: &PrivateConfig, group: &'a Group) -> SignedGroup<'a> {
+        SignedGroup {
+            id: &group.id,
+            description: &group.description,
+            members: if private_config.is_delegated(&group.name) {
+                None
+            } else {
+                Some(&group.members)
+            },
+            subgroups: &group.subgroups,
+            managers: &group.managers,
+            created_at: &group.created_at,
+        }
+    }
+}
+
+fn verifying_key(public_key: &str) -> Option<VerifyingKey> {
+    let key_bytes: [u8; 32] = BASE64.decode(public_key).ok()?.try_into().ok()?;
+    VerifyingKey::from_bytes(&key_bytes).ok()
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const ADMIN_PASSWORD: &str = "admin-password";
+    const ALICE_PASSWORD: &str = "alice-password";
+
+    fn user(name: &str) -> User {
+        User {
+            id: Uuid::new_v4(),
+            username: name.to_string(),
+            email: None,
+            password_hash: format!("{}-hash", name),
+            salt: String::new(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            last_access: None,
+            is_admin: false,
+            invite_token_hash: None,
+            expires_at: None,
+            disabled: false,
+            public_key: None,
+            signing_key: None,
+        }
+    }
+
+    /// A signed vault where alice manages group "qa", which has bob as its member.
+    fn vault() -> (SmolcaseConfig, PrivateConfig) {
+        let mut alice = user("alice");
+        VaultSigner::new_user_key(&mut alice, ALICE_PASSWORD).unwrap();
+
+        let qa = Group {
+            id: Uuid::new_v4(),
+            name: "qa".to_string(),
+            description: None,
+            members: vec!["bob".to_string()],
+            subgroups: Vec::new(),
+            managers: vec!["alice".to_string()],
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            membership_signature: None,
+        };
+
+        let mut public_config = SmolcaseConfig {
+            version: "1.0.0".to_string(),
+            project_name: "demo".to_string(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            admin_key_hash: "admin-hash".to_string(),
+            master_key_hash: "master-hash".to_string(),
+            encrypted_data: EncryptedData::default(),
+            settings: ProjectSettings::default(),
+            revision: 1,
+            admin_public_key: None,
+            admin_signing_key: None,
+            signature: None,
+        };
+        let mut private_config = PrivateConfig {
+            users: HashMap::from([
+                ("alice".to_string(), alice),
+                ("bob".to_string(), user("bob")),
+                ("carol".to_string(), user("carol")),
+            ]),
+            groups: HashMap::from([("qa".to_string(), qa)]),
+            secrets: HashMap::new(),
+            encrypted_secrets: EncryptedData::default(),
+        };
+
+        VaultSigner::enable(&mut public_config, &mut private_config, ADMIN_PASSWORD).unwrap();
+        (public_config, private_config)
+    }
+
+    #[test]
+    fn signed_vault_verifies() {
+        let (public_config, private_config) = vault();
+
+        assert!(VaultSigner::verify(&public_config, &private_config).is_ok());
+        // The admin endorsed the members the group started with
+        let signature = private_config.groups["qa"]
+            .membership_signature
+            .as_ref()
+            .unwrap();
+        assert_eq!(signature.manager, None);
+        assert_eq!(signature.counter, 1);
+    }
+
+    #[test]
+    fn admin_content_changes_need_the_admin_key() {
+        let (public_config, mut private_config) = vault();
+        private_config.users.get_mut("bob").unwrap().is_admin = true;
+        assert!(VaultSigner::verify_content(&public_config, &private_config).is_err());
+
+        // The revision is signed as well, so it can't be moved back by hand
+        let (mut public_config, private_config) = vault();
+        public_config.revision = 0;
+        assert!(VaultSigner::verify(&public_config, &private_config).is_err());
+    }
+
+    #[test]
+    fn managers_sign_membership_changes() {
+        let (public_config, mut private_config) = vault();
+        private_config
+            .groups
+            .get_mut("qa")
+            .unwrap()
+            .members
+            .push("carol".to_string());
+
+        // Delegated members are outside the admin signature but need their own
+        assert!(VaultSigner::verify_content(&public_config, &private_config).is_ok());
+        assert_eq!(
+            VaultSigner::unsigned_groups(&public_config, &private_config),
+            ["qa"]
+        );
+
+        VaultSigner::sign_membership(
+            &public_config,
+            &mut private_config,
+            "qa",
+            "alice",
+            ALICE_PASSWORD,
+        )
+        .unwrap();
+        assert!(VaultSigner::verify(&public_config, &private_config).is_ok());
+
+        let signature = private_config.groups["qa"]
+            .membership_signature
+            .as_ref()
+            .unwrap();
+        assert_eq!(signature.manager.as_deref(), Some("alice"));
+        assert_eq!(signature.counter, 2);
+    }
+
+    #[test]
+    fn only_managers_sign_membership() {
+        let (public_config, mut private_config) = vault();
+        let mut bob = user("bob");
+        VaultSigner::new_user_key(&mut bob, "bob-password").unwrap();
+        private_config.users.insert("bob".to_string(), bob);
+
+        assert!(
+            VaultSigner::sign_membership(
+                &public_config,
+                &mut private_config,
+                "qa",
+                "bob",
+                "bob-password",
+            )
+            .is_err()
+        );
+        assert!(
+            VaultSigner::sign_membership(
+                &public_config,
+                &mut private_config,
+                "qa",
+                "alice",
+                "wrong-password",
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn membership_counter_is_signed() {
+        // Raising the counter of an old signature, to get past the counter a
+        // machine has seen, invalidates it
+        let (public_config, mut private_config) = vault();
+        let group = private_config.groups.get_mut("qa").unwrap();
+        group.membership_signature.as_mut().unwrap().counter = 5;
+
+        assert_eq!(
+            VaultSigner::unsigned_groups(&public_config, &private_config),
+            ["qa"]
+        );
+    }
+}
@@ -0,0 +1,374 @@
+use crate::config::{CONFIG_DIR, CONFIG_FILE, ConfigManager};
+use crate::git::{FileRevision, GitManager};
+use crate::s3_storage::S3Storage;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+/// Local, committed file that says where the vault lives. Without it the
+/// vault files sit next to it in the working tree, as they always have.
+pub const STORAGE_FILE: &str = ".smolcase-storage.yml";
+
+/// Locks older than this were left behind by a crashed process
+pub const STALE_LOCK_SECS: i64 = 300;
+const LOCK_ATTEMPTS: u32 = 20;
+const LOCK_RETRY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum StorageConfig {
+    /// `.smolcase.yml` and friends in the project directory, versioned by Git
+    #[default]
+    File,
+    /// A shared directory outside the repository (network drive, synced folder)
+    Directory { path: PathBuf },
+    /// An S3-compatible bucket; credentials come from AWS_ACCESS_KEY_ID and
+    /// AWS_SECRET_ACCESS_KEY
+    S3 {
+        endpoint: String,
+        bucket: String,
+        #[serde(default)]
+        prefix: String,
+        #[serde(default = "default_region")]
+        region: String,
+    },
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+/// Where the vault, access log and audit log are read from and written to.
+pub trait VaultStorage {
+    fn load(&self, file: &str) -> Result<Option<String>>;
+
+    fn save(&self, file: &str, content: &str) -> Result<()>;
+
+    /// Held while a vault is checked and written, so two writers can't both
+    /// build on the same revision.
+    fn lock(&self) -> Result<StorageLock<'_>>;
+
+    /// Saved versions of the vault, newest first.
+    fn revisions(&self, limit: Option<usize>) -> Result<Vec<FileRevision>>;
+
+    fn describe(&self) -> String;
+
+    /// Whether the vault is versioned by the Git repository around it.
+    fn uses_git(&self) -> bool {
+        false
+    }
+}
+
+/// Releases the storage lock when dropped.
+pub struct StorageLock<'a> {
+    release: Option<Box<dyn FnOnce() + 'a>>,
+}
+
+impl<'a> StorageLock<'a> {
+    pub fn new(release: impl FnOnce() + 'a) -> Self {
+        Self {
+            release: Some(Box::new(release)),
+        }
+    }
+}
+
+impl Drop for StorageLock<'_> {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            release();
+        }
+    }
+}
+
+pub struct Storage;
+
+impl Storage {
+    pub fn config_path() -> Result<PathBuf> {
+        let current_dir = std::env::current_dir()
+            .map_err(|e| anyhow!("Failed to get current directory: {}", e))?;
+        Ok(current_dir.join(STORAGE_FILE))
+    }
+
+    pub fn load_config() -> Result<StorageConfig> {
+        let path = Self::config_path()?;
+        if !path.exists() {
+            return Ok(StorageConfig::File);
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", STORAGE_FILE, e))?;
+        serde_yaml::from_str(&content).map_err(|e| anyhow!("Invalid {}: {}", STORAGE_FILE, e))
+    }
+
+    pub fn save_config(config: &StorageConfig) -> Result<()> {
+        let path = Self::config_path()?;
+        if matches!(config, StorageConfig::File) {
+            if path.exists() {
+                fs::remove_file(&path)?;
+            }
+            return Ok(());
+        }
+
+        fs::write(&path, serde_yaml::to_string(config)?)
+            .map_err(|e| anyhow!("Failed to write {}: {}", STORAGE_FILE, e))
+    }
+
+    /// The backend configured for the project in the current directory.
+    pub fn open() -> Result<Box<dyn VaultStorage>> {
+        Self::from_config(&Self::load_config()?)
+    }
+
+    pub fn from_config(config: &StorageConfig) -> Result<Box<dyn VaultStorage>> {
+        Ok(match config {
+            StorageConfig::File => Box::new(FileStorage {
+                root: std::env::current_dir()?,
+            }),
+            StorageConfig::Directory { path } => Box::new(DirectoryStorage { root: path.clone() }),
+            StorageConfig::S3 {
+                endpoint,
+                bucket,
+                prefix,
+                region,
+            } => Box::new(S3Storage::new(endpoint, bucket, prefix, region)?),
+        })
+    }
+
+    /// Object name for a saved copy of `content`; sorts oldest first.
+    pub fn revision_name(content: &str) -> String {
+        let revision = ConfigManager::parse_config(content)
+            .map(|c| c.revision)
+            .unwrap_or_default();
+        format!(
+            "revisions/{:010}-{}.yml",
+            revision,
+            Utc::now().format("%Y%m%dT%H%M%S%3fZ")
+        )
+    }
+
+    /// Turns saved copies (oldest first) into history entries, newest first.
+    pub fn history(
+        saved: Vec<(String, String)>,
+        limit: Option<usize>,
+        origin: &str,
+    ) -> Vec<FileRevision> {
+        let mut revisions = Vec::new();
+        let mut parent_content = None;
+
+        for (name, content) in saved {
+            let stem = name
+                .trim_start_matches("revisions/")
+                .trim_end_matches(".yml");
+            let (revision, time) = stem.split_once('-').unwrap_or((stem, ""));
+            let time = NaiveDateTime::parse_from_str(time, "%Y%m%dT%H%M%S%3fZ")
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                .unwrap_or_default();
+            let revision = revision.trim_start_matches('0');
+
+            revisions.push(FileRevision {
+                id: format!("{:0>8}", revision),
+                author: origin.to_string(),
+                time,
+                message: format!(
+                    "Revision {}",
+                    if revision.is_empty() { "0" } else { revision }
+                ),
+                content: Some(content.clone()),
+                parent_content: parent_content.replace(content),
+            });
+        }
+
+        revisions.reverse();
+        if let Some(limit) = limit {
+            revisions.truncate(limit);
+        }
+        revisions
+    }
+
+    /// Who holds a lock, recorded in the lock itself.
+    pub fn lock_owner() -> String {
+        let host = hostname::get()
+            .map(|h| h.to_string_lossy().to_string())
+            .unwrap_or_else(|_| "unknown".to_string());
+        format!(
+            "{} pid {} at {}",
+            host,
+            std::process::id(),
+            Utc::now().to_rfc3339()
+        )
+    }
+
+    /// Whether a lock written by `lock_owner` has been held too long.
+    /// Unreadable locks are never broken automatically.
+    pub fn is_stale(owner: &str) -> bool {
+        owner
+            .rsplit(" at ")
+            .next()
+            .and_then(|t| DateTime::parse_from_rfc3339(t.trim()).ok())
+            .is_some_and(|t| (Utc::now() - t.with_timezone(&Utc)).num_seconds() > STALE_LOCK_SECS)
+    }
+
+    /// Retries `try_lock` until it succeeds or the lock stays busy.
+    pub fn acquire(mut try_lock: impl FnMut() -> Result<bool>, what: &str) -> Result<()> {
+        for _ in 0..LOCK_ATTEMPTS {
+            if try_lock()? {
+                return Ok(());
+            }
+            thread::sleep(LOCK_RETRY);
+        }
+
+        Err(anyhow!(
+            "The vault is locked by another smolcase process ({}). Try again shortly.",
+            what
+        ))
+    }
+}
+
+/// The original layout: vault files in the project directory, history in Git.
+pub struct FileStorage {
+    root: PathBuf,
+}
+
+impl VaultStorage for FileStorage {
+    fn load(&self, file: &str) -> Result<Option<String>> {
+        read_optional(&self.root.join(file))
+    }
+
+    fn save(&self, file: &str, content: &str) -> Result<()> {
+        fs::write(self.root.join(file), content)
+            .map_err(|e| anyhow!("Failed to write {}: {}", file, e))
+    }
+
+    fn lock(&self) -> Result<StorageLock<'_>> {
+        let dir = self.root.join(CONFIG_DIR);
+        fs::create_dir_all(&dir)?;
+        lock_file(dir.join("lock"))
+    }
+
+    fn revisions(&self, limit: Option<usize>) -> Result<Vec<FileRevision>> {
+        if !GitManager::is_git_repo(&self.root) {
+            return Ok(Vec::new());
+        }
+        GitManager::file_history(&self.root, CONFIG_FILE, limit)
+    }
+
+    fn describe(&self) -> String {
+        "file (project directory)".to_string()
+    }
+
+    fn uses_git(&self) -> bool {
+        true
+    }
+}
+
+/// Vault files in a shared directory, with every saved vault kept under
+/// `revisions/`.
+pub struct DirectoryStorage {
+    root: PathBuf,
+}
+
+impl VaultStorage for DirectoryStorage {
+    fn load(&self, file: &str) -> Result<Option<String>> {
+        read_optional(&self.root.join(file))
+    }
+
+    fn save(&self, file: &str, content: &str) -> Result<()> {
+        fs::create_dir_all(self.root.join("revisions"))
+            .map_err(|e| anyhow!("Cannot create {}: {}", self.root.display(), e))?;
+
+        if file == CONFIG_FILE {
+            fs::write(self.root.join(Storage::revision_name(content)), content)?;
+        }
+        // Write then rename, so readers never see half a vault
+        let target = self.root.join(file);
+        let partial = self.root.join(format!("{}.partial", file));
+        fs::write(&partial, content).map_err(|e| anyhow!("Failed to write {}: {}", file, e))?;
+        fs::rename(&partial, &target).map_err(|e| anyhow!("Failed to write {}: {}", file, e))
+    }
+
+    fn lock(&self) -> Result<StorageLock<'_>> {
+        fs::create_dir_all(&self.root)
+            .map_err(|e| anyhow!("Cannot create {}: {}", self.root.display(), e))?;
+        lock_file(self.root.join(".lock"))
+    }
+
+    fn revisions(&self, limit: Option<usize>) -> Result<Vec<FileRevision>> {
+        let dir = self.root.join("revisions");
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|name| name.ends_with(".yml"))
+            .collect();
+        names.sort();
+
+        // One extra so the oldest shown revision still has its parent
+        let skip = limit.map_or(0, |limit| names.len().saturating_sub(limit + 1));
+        let mut saved = Vec::new();
+        for name in names.into_iter().skip(skip) {
+            let content = fs::read_to_string(dir.join(&name))?;
+            saved.push((format!("revisions/{}", name), content));
+        }
+
+        Ok(Storage::history(saved, limit, &self.describe()))
+    }
+
+    fn describe(&self) -> String {
+        format!("directory {}", self.root.display())
+    }
+}
+
+fn read_optional(path: &Path) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    fs::read_to_string(path)
+        .map(Some)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))
+}
+
+fn lock_file<'a>(path: PathBuf) -> Result<StorageLock<'a>> {
+    let owner = Storage::lock_owner();
+    Storage::acquire(
+        || match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(owner.as_bytes())?;
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                let stale = fs::read_to_string(&path).unwrap_or_default();
+                if Storage::is_stale(&stale) {
+                    remove_lock_if(&path, &stale);
+                }
+                Ok(false)
+            }
+            Err(e) => Err(anyhow!("Cannot lock {}: {}", path.display(), e)),
+        },
+        &path.display().to_string(),
+    )?;
+
+    Ok(StorageLock::new(move || remove_lock_if(&path, &owner)))
+}
+
+/// Removes the lock at `path` only if it still holds `owner`. The lock is
+/// first moved aside, so a lock taken by someone else in the meantime is
+/// never deleted, only put back.
+fn remove_lock_if(path: &Path, owner: &str) {
+    let aside = path.with_extension(format!("released-{}", std::process::id()));
+    if fs::rename(path, &aside).is_err() {
+        return;
+    }
+
+    if fs::read_to_string(&aside).is_ok_and(|content| content != owner) {
+        // hard_link never replaces a lock created since the rename
+        let _ = fs::hard_link(&aside, path);
+    }
+    let _ = fs::remove_file(&aside);
+}
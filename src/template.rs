@@ -0,0 +1,587 @@
+use crate::secret_scan::url_encode;
+use anyhow::{Result, anyhow};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use regex::Regex;
+use std::collections::HashMap;
+
+/// A vault secret available to a template.
+pub struct TemplateSecret {
+    pub key: String,
+    pub value: Vec<u8>,
+    pub tags: Vec<String>,
+}
+
+pub struct Rendered {
+    pub output: String,
+    /// Vault keys the template read
+    pub used: Vec<String>,
+    /// Names that resolved to nothing and had no default
+    pub missing: Vec<String>,
+}
+
+/// `apply` templates:
+///
+/// ```text
+/// {{ KEY }}  {{ KEY | base64 }}  {{ KEY | default: "x" }}   (json, yaml, urlencode, shell_quote)
+/// {% if KEY %}...{% else %}...{% endif %}                  ({% if not KEY %} too)
+/// {% for s in tag "db" %}{{ s.key }}={{ s.value }}{% endfor %}   (or: in secrets)
+/// ```
+///
+/// Names are looked up in the vault (file secrets give their content), then in
+/// the process environment. Anything else in braces, such as Helm's
+/// `{{ .Values.x }}`, is left as it is.
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+enum Node {
+    Text(String),
+    Value {
+        name: String,
+        filters: Vec<Filter>,
+    },
+    If {
+        name: String,
+        negate: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+    For {
+        var: String,
+        tag: Option<String>,
+        body: Vec<Node>,
+    },
+}
+
+enum Filter {
+    Base64,
+    Json,
+    Yaml,
+    UrlEncode,
+    ShellQuote,
+    Default(String),
+}
+
+/// An `{% if %}` or `{% for %}` still open while parsing
+enum Block {
+    If {
+        name: String,
+        negate: bool,
+        then: Vec<Node>,
+        otherwise: Option<Vec<Node>>,
+        line: usize,
+    },
+    For {
+        var: String,
+        tag: Option<String>,
+        body: Vec<Node>,
+        line: usize,
+    },
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self> {
+        let tag = Regex::new(r"(?s)\{\{(.*?)\}\}|\{%(.*?)%\}").expect("valid regex");
+        let name = Regex::new(r"^[A-Za-z0-9_][A-Za-z0-9_.\-]*$").expect("valid regex");
+
+        let mut root: Vec<Node> = Vec::new();
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut last = 0;
+
+        for caps in tag.captures_iter(source) {
+            let whole = caps.get(0).expect("whole match");
+            let line = source[..whole.start()].matches('\n').count() + 1;
+            let verbatim = Node::Text(whole.as_str().to_string());
+
+            push(
+                &mut root,
+                &mut blocks,
+                Node::Text(source[last..whole.start()].to_string()),
+            );
+            last = whole.end();
+
+            let node = if let Some(inner) = caps.get(1) {
+                let mut parts = split_pipes(inner.as_str()).into_iter();
+                let value_name = parts.next().unwrap_or_default();
+                if !name.is_match(&value_name) {
+                    Some(verbatim)
+                } else {
+                    let filters = parts
+                        .map(|p| parse_filter(&p))
+                        .collect::<Result<Vec<_>>>()
+                        .map_err(|e| anyhow!("Line {}: {}", line, e))?;
+                    Some(Node::Value {
+                        name: value_name,
+                        filters,
+                    })
+                }
+            } else {
+                let words: Vec<&str> = caps[2].split_whitespace().collect();
+                match words.as_slice() {
+                    ["if", "not", cond] | ["if", cond] if name.is_match(cond) => {
+                        blocks.push(Block::If {
+                            name: cond.to_string(),
+                            negate: words.len() == 3,
+                            then: Vec::new(),
+                            otherwise: None,
+                            line,
+                        });
+                        None
+                    }
+                    ["else"] => match blocks.last_mut() {
+                        Some(Block::If { otherwise, .. }) if otherwise.is_none() => {
+                            *otherwise = Some(Vec::new());
+                            None
+                        }
+                        _ => return Err(anyhow!("Line {}: {{% else %}} outside {{% if %}}", line)),
+                    },
+                    ["endif"] => match blocks.pop() {
+                        Some(Block::If {
+                            name,
+                            negate,
+                            then,
+                            otherwise,
+                            ..
+                        }) => Some(Node::If {
+                            name,
+                            negate,
+                            then,
+                            otherwise: otherwise.unwrap_or_default(),
+                        }),
+                        _ => {
+                            return Err(anyhow!("Line {}: {{% endif %}} without {{% if %}}", line));
+                        }
+                    },
+                    ["for", var, "in", "secrets"] => {
+                        blocks.push(Block::For {
+                            var: var.to_string(),
+                            tag: None,
+                            body: Vec::new(),
+                            line,
+                        });
+                        None
+                    }
+                    ["for", var, "in", "tag", tag] => {
+                        blocks.push(Block::For {
+                            var: var.to_string(),
+                            tag: Some(unquote(tag).to_string()),
+                            body: Vec::new(),
+                            line,
+                        });
+                        None
+                    }
+                    ["endfor"] => match blocks.pop() {
+                        Some(Block::For { var, tag, body, .. }) => {
+                            Some(Node::For { var, tag, body })
+                        }
+                        _ => {
+                            return Err(anyhow!(
+                                "Line {}: {{% endfor %}} without {{% for %}}",
+                                line
+                            ));
+                        }
+                    },
+                    // Not ours; another template language may handle it later
+                    _ => Some(verbatim),
+                }
+            };
+
+            if let Some(node) = node {
+                push(&mut root, &mut blocks, node);
+            }
+        }
+        push(
+            &mut root,
+            &mut blocks,
+            Node::Text(source[last..].to_string()),
+        );
+
+        match blocks.last() {
+            Some(Block::If { line, .. }) => {
+                Err(anyhow!("Line {}: {{% if %}} is never closed", line))
+            }
+            Some(Block::For { line, .. }) => {
+                Err(anyhow!("Line {}: {{% for %}} is never closed", line))
+            }
+            None => Ok(Self { nodes: root }),
+        }
+    }
+
+    pub fn render(&self, secrets: &[TemplateSecret]) -> Result<Rendered> {
+        let mut renderer = Renderer {
+            secrets,
+            by_key: secrets.iter().map(|s| (s.key.as_str(), s)).collect(),
+            used: Vec::new(),
+            missing: Vec::new(),
+        };
+
+        let mut output = String::new();
+        renderer.render(&self.nodes, &mut Vec::new(), &mut output)?;
+
+        Ok(Rendered {
+            output,
+            used: renderer.used,
+            missing: renderer.missing,
+        })
+    }
+}
+
+struct Renderer<'a> {
+    secrets: &'a [TemplateSecret],
+    by_key: HashMap<&'a str, &'a TemplateSecret>,
+    used: Vec<String>,
+    missing: Vec<String>,
+}
+
+impl<'a> Renderer<'a> {
+    /// `scope` holds the loop variables in effect, innermost last.
+    fn render<'n>(
+        &mut self,
+        nodes: &'n [Node],
+        scope: &mut Vec<(&'n str, &'a TemplateSecret)>,
+        output: &mut String,
+    ) -> Result<()> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => output.push_str(text),
+                Node::Value { name, filters } => {
+                    let mut value = self.lookup(name, scope);
+                    for filter in filters {
+                        value =
+                            apply_filter(filter, value).map_err(|e| anyhow!("{}: {}", name, e))?;
+                    }
+
+                    match value {
+                        // Binary file secrets would be corrupted, not just shown oddly
+                        Some(value) => output
+                            .push_str(&String::from_utf8(value).map_err(|_| {
+                            anyhow!(
+                                "{} is not UTF-8 text; use {{{{ {} | base64 }}}} for binary values",
+                                name,
+                                name
+                            )
+                        })?),
+                        None => {
+                            if !self.missing.contains(name) {
+                                self.missing.push(name.clone());
+                            }
+                            output.push_str(&format!("{{{{MISSING:{}}}}}", name));
+                        }
+                    }
+                }
+                Node::If {
+                    name,
+                    negate,
+                    then,
+                    otherwise,
+                } => {
+                    let set = self.lookup(name, scope).is_some_and(|v| !v.is_empty());
+                    let branch = if set != *negate { then } else { otherwise };
+                    self.render(branch, scope, output)?;
+                }
+                Node::For { var, tag, body } => {
+                    let secrets = self.secrets;
+                    for secret in secrets
+                        .iter()
+                        .filter(|s| tag.as_ref().is_none_or(|t| s.tags.contains(t)))
+                    {
+                        scope.push((var.as_str(), secret));
+                        let rendered = self.render(body, scope, output);
+                        scope.pop();
+                        rendered?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn lookup(&mut self, name: &str, scope: &[(&str, &TemplateSecret)]) -> Option<Vec<u8>> {
+        let (var, field) = name.split_once('.').unwrap_or((name, "value"));
+        if let Some((_, secret)) = scope.iter().rev().find(|(v, _)| *v == var) {
+            return match field {
+                "key" => Some(secret.key.clone().into_bytes()),
+                "value" => {
+                    self.mark_used(&secret.key);
+                    Some(secret.value.clone())
+                }
+                _ => None,
+            };
+        }
+
+        if let Some(&secret) = self.by_key.get(name) {
+            self.mark_used(&secret.key);
+            return Some(secret.value.clone());
+        }
+
+        std::env::var(name).ok().map(String::into_bytes)
+    }
+
+    fn mark_used(&mut self, key: &str) {
+        if !self.used.iter().any(|k| k == key) {
+            self.used.push(key.to_string());
+        }
+    }
+}
+
+fn apply_filter(filter: &Filter, value: Option<Vec<u8>>) -> Result<Option<Vec<u8>>> {
+    let Some(value) = value else {
+        return Ok(match filter {
+            Filter::Default(default) => Some(default.clone().into_bytes()),
+            _ => None,
+        });
+    };
+    let text = || {
+        std::str::from_utf8(&value)
+            .map_err(|_| anyhow!("not UTF-8 text, so only the base64 filter applies"))
+    };
+
+    Ok(Some(match filter {
+        Filter::Base64 => STANDARD.encode(&value).into_bytes(),
+        Filter::Json => serde_json::to_string(text()?)?.into_bytes(),
+        Filter::Yaml => {
+            // Plain or quoted scalar as serde_yaml writes it, but never a
+            // block scalar, which can't sit inline after `key: `
+            let yaml = serde_yaml::to_string(text()?)?;
+            let yaml = yaml.trim_end();
+            if yaml.contains('\n') {
+                serde_json::to_string(text()?)?.into_bytes()
+            } else {
+                yaml.as_bytes().to_vec()
+            }
+        }
+        Filter::UrlEncode => url_encode(text()?).into_bytes(),
+        Filter::ShellQuote => format!("'{}'", text()?.replace('\'', r"'\''")).into_bytes(),
+        Filter::Default(default) if value.is_empty() => default.clone().into_bytes(),
+        Filter::Default(_) => value,
+    }))
+}
+
+fn push(root: &mut Vec<Node>, blocks: &mut [Block], node: Node) {
+    let nodes = match blocks.last_mut() {
+        Some(Block::If {
+            otherwise: Some(nodes),
+            ..
+        }) => nodes,
+        Some(Block::If { then, .. }) => then,
+        Some(Block::For { body, .. }) => body,
+        None => root,
+    };
+    nodes.push(node);
+}
+
+fn parse_filter(filter: &str) -> Result<Filter> {
+    let (name, arg) = match filter.split_once(':') {
+        Some((name, arg)) => (name.trim(), Some(unquote(arg.trim()))),
+        None => (filter.trim(), None),
+    };
+
+    Ok(match (name, arg) {
+        ("base64", None) => Filter::Base64,
+        ("json", None) => Filter::Json,
+        ("yaml", None) => Filter::Yaml,
+        ("urlencode", None) => Filter::UrlEncode,
+        ("shell_quote", None) => Filter::ShellQuote,
+        ("default", Some(value)) => Filter::Default(value.to_string()),
+        ("default", None) => return Err(anyhow!("default needs a value, e.g. default: \"x\"")),
+        _ => return Err(anyhow!("Unknown filter '{}'", filter)),
+    })
+}
+
+/// Splits `KEY | default: "a|b"` on the pipes outside quotes.
+fn split_pipes(expression: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut quote = None;
+
+    for c in expression.chars() {
+        match (c, quote) {
+            ('|', None) => {
+                parts.push(String::new());
+                continue;
+            }
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            _ => {}
+        }
+        parts.last_mut().expect("never empty").push(c);
+    }
+
+    parts.iter().map(|p| p.trim().to_string()).collect()
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(key: &str, value: &[u8], tags: &[&str]) -> TemplateSecret {
+        TemplateSecret {
+            key: key.to_string(),
+            value: value.to_vec(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    fn render(source: &str, secrets: &[TemplateSecret]) -> Result<Rendered> {
+        Template::parse(source)?.render(secrets)
+    }
+
+    fn output(source: &str, secrets: &[TemplateSecret]) -> String {
+        render(source, secrets).unwrap().output
+    }
+
+    fn error(source: &str) -> String {
+        Template::parse(source).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn substitutes_values_and_records_used_keys() {
+        let secrets = [
+            secret("DB_URL", b"postgres://db", &[]),
+            secret("UNUSED", b"x", &[]),
+        ];
+        let rendered = render("url={{ DB_URL }} again={{DB_URL}}", &secrets).unwrap();
+
+        assert_eq!(rendered.output, "url=postgres://db again=postgres://db");
+        assert_eq!(rendered.used, ["DB_URL"]);
+        assert!(rendered.missing.is_empty());
+    }
+
+    #[test]
+    fn marks_missing_names() {
+        let rendered = render("a={{ SMOLCASE_TEST_NOT_SET }}", &[]).unwrap();
+
+        assert_eq!(rendered.output, "a={{MISSING:SMOLCASE_TEST_NOT_SET}}");
+        assert_eq!(rendered.missing, ["SMOLCASE_TEST_NOT_SET"]);
+    }
+
+    #[test]
+    fn leaves_other_template_syntax_alone() {
+        let source = "{{ .Values.image }} {% raw %} {{ $x }}";
+
+        assert_eq!(output(source, &[]), source);
+    }
+
+    #[test]
+    fn applies_filters() {
+        let secrets = [secret("PW", b"it's a:b", &[]), secret("EMPTY", b"", &[])];
+
+        assert_eq!(output("{{ PW | base64 }}", &secrets), "aXQncyBhOmI=");
+        assert_eq!(output("{{ PW | json }}", &secrets), r#""it's a:b""#);
+        assert_eq!(output("{{ PW | yaml }}", &secrets), "it's a:b");
+        assert_eq!(output("{{ PW | urlencode }}", &secrets), "it%27s%20a%3Ab");
+        assert_eq!(output("{{ PW | shell_quote }}", &secrets), r"'it'\''s a:b'");
+        assert_eq!(output(r#"{{ EMPTY | default: "x" }}"#, &secrets), "x");
+        assert_eq!(
+            output(
+                r#"{{ SMOLCASE_TEST_NOT_SET | default: 'a|b' | base64 }}"#,
+                &secrets
+            ),
+            "YXxi"
+        );
+    }
+
+    #[test]
+    fn yaml_filter_never_emits_block_scalars() {
+        let secrets = [secret("CERT", b"line one\nline two", &[])];
+
+        assert_eq!(
+            output("cert: {{ CERT | yaml }}", &secrets),
+            r#"cert: "line one\nline two""#
+        );
+    }
+
+    #[test]
+    fn binary_values_need_base64() {
+        let secrets = [secret("tls.key", &[0xff, 0x00, 0xfe], &[])];
+
+        assert_eq!(output("{{ tls.key | base64 }}", &secrets), "/wD+");
+        let raw = render("{{ tls.key }}", &secrets).err().unwrap().to_string();
+        assert!(raw.contains("{{ tls.key | base64 }}"), "{}", raw);
+        assert!(render("{{ tls.key | json }}", &secrets).is_err());
+    }
+
+    #[test]
+    fn splits_pipes_outside_quotes() {
+        assert_eq!(
+            split_pipes(r#" KEY | default: "a|b" | json "#),
+            ["KEY", r#"default: "a|b""#, "json"]
+        );
+        assert_eq!(split_pipes("KEY|default:'x|y'"), ["KEY", "default:'x|y'"]);
+        assert_eq!(split_pipes("KEY"), ["KEY"]);
+    }
+
+    #[test]
+    fn nested_if_and_else() {
+        let secrets = [secret("A", b"1", &[]), secret("EMPTY", b"", &[])];
+        let source = "{% if A %}a{% if EMPTY %}e{% else %}!e{% endif %}{% else %}!a{% endif %}\
+                      {% if not SMOLCASE_TEST_NOT_SET %} unset{% endif %}";
+
+        assert_eq!(output(source, &secrets), "a!e unset");
+    }
+
+    #[test]
+    fn loops_over_tagged_secrets() {
+        let secrets = [
+            secret("DB_HOST", b"db", &["db"]),
+            secret("API_KEY", b"k", &[]),
+            secret("DB_USER", b"app", &["db"]),
+        ];
+
+        assert_eq!(
+            output(
+                r#"{% for s in tag "db" %}{{ s.key }}={{ s.value }};{% endfor %}"#,
+                &secrets
+            ),
+            "DB_HOST=db;DB_USER=app;"
+        );
+        assert_eq!(
+            output("{% for s in secrets %}{{ s.key }} {% endfor %}", &secrets),
+            "DB_HOST API_KEY DB_USER "
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        assert_eq!(
+            error("a\n{% if A %}\nb"),
+            "Line 2: {% if %} is never closed"
+        );
+        assert_eq!(
+            error("\n\n{% else %}"),
+            "Line 3: {% else %} outside {% if %}"
+        );
+        assert_eq!(
+            error("{% if A %}{% else %}{% else %}{% endif %}"),
+            "Line 1: {% else %} outside {% if %}"
+        );
+        assert_eq!(
+            error("x\n{% endif %}"),
+            "Line 2: {% endif %} without {% if %}"
+        );
+        assert_eq!(
+            error("{% for s in secrets %}\n{% endif %}"),
+            "Line 2: {% endif %} without {% if %}"
+        );
+        assert_eq!(
+            error("\n{% for s in secrets %}"),
+            "Line 2: {% for %} is never closed"
+        );
+        assert_eq!(
+            error("\n\n\n{{ A | upper }}"),
+            "Line 4: Unknown filter 'upper'"
+        );
+        assert_eq!(
+            error("{{ A | default }}"),
+            "Line 1: default needs a value, e.g. default: \"x\""
+        );
+    }
+}
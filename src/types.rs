@@ -1,5 +1,7 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -10,6 +12,60 @@ pub struct SmolcaseConfig {
     pub admin_key_hash: String,        // Only for admin verification
     pub master_key_hash: String,       // Only for master key verification
     pub encrypted_data: EncryptedData, // ALL sensitive data encrypted
+    #[serde(default)]
+    pub settings: ProjectSettings,
+    #[serde(default)]
+    pub revision: u64, // Moves forward with every signed change, so rollbacks are noticed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin_public_key: Option<String>, // Ed25519 key that signs admin-controlled content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub admin_signing_key: Option<EncryptedData>, // Its private half, encrypted with the admin password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+// Project-wide behaviour - public, changed by admins with 'smolcase settings'
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProjectSettings {
+    /// Commit the vault after every mutating command
+    #[serde(default)]
+    pub auto_commit: bool,
+    /// Pull and push after each auto-commit
+    #[serde(default)]
+    pub auto_push: bool,
+}
+
+impl ProjectSettings {
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("auto_commit", self.auto_commit.to_string()),
+            ("auto_push", self.auto_push.to_string()),
+        ]
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "auto_commit" => self.auto_commit = parse_bool(key, value)?,
+            "auto_push" => self.auto_push = parse_bool(key, value)?,
+            _ => {
+                let known: Vec<_> = self.entries().into_iter().map(|(k, _)| k).collect();
+                return Err(anyhow!(
+                    "Unknown setting '{}'. Available: {}",
+                    key,
+                    known.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(anyhow!("Setting '{}' expects true or false", key)),
+    }
 }
 
 // PRIVATE config - encrypted with master key
@@ -21,6 +77,182 @@ pub struct PrivateConfig {
     pub encrypted_secrets: EncryptedData, // Double-encrypted secret values
 }
 
+impl PrivateConfig {
+    /// Users in `group` or any group nested inside it. Cycles are ignored.
+    pub fn effective_members(&self, group: &str) -> BTreeSet<String> {
+        let mut members = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![group.to_string()];
+
+        while let Some(name) = pending.pop() {
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+            if let Some(group) = self.groups.get(&name) {
+                members.extend(group.members.iter().cloned());
+                pending.extend(group.subgroups.iter().cloned());
+            }
+        }
+
+        members
+    }
+
+    /// True when `inner` is `outer` or is reachable from it through nesting.
+    pub fn group_contains(&self, outer: &str, inner: &str) -> bool {
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![outer.to_string()];
+
+        while let Some(name) = pending.pop() {
+            if name == inner {
+                return true;
+            }
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+            if let Some(group) = self.groups.get(&name) {
+                pending.extend(group.subgroups.iter().cloned());
+            }
+        }
+
+        false
+    }
+
+    /// True when `username` manages `group` directly or through a group that contains it.
+    pub fn can_manage_group(&self, username: &str, group: &str) -> bool {
+        self.groups.values().any(|manager_group| {
+            manager_group.managers.iter().any(|m| m == username)
+                && self.group_contains(&manager_group.name, group)
+        })
+    }
+
+    /// True when some manager can change the members of `group`. Those members
+    /// are signed by a manager instead of with the rest of the vault.
+    pub fn is_delegated(&self, group: &str) -> bool {
+        self.groups
+            .values()
+            .any(|g| !g.managers.is_empty() && self.group_contains(&g.name, group))
+    }
+
+    /// Describes every place that still refers to `username`.
+    pub fn user_references(&self, username: &str) -> Vec<String> {
+        let mut references = Vec::new();
+
+        for group in self.groups.values() {
+            if group.members.iter().any(|m| m == username) {
+                references.push(format!("member of group '{}'", group.name));
+            }
+            if group.managers.iter().any(|m| m == username) {
+                references.push(format!("manager of group '{}'", group.name));
+            }
+        }
+        for secret in self.secrets.values() {
+            if secret.permissions.users.iter().any(|u| u == username) {
+                references.push(format!("permission on secret '{}'", secret.key));
+            }
+        }
+
+        references.sort();
+        references
+    }
+
+    /// Describes every place that still refers to `group`.
+    pub fn group_references(&self, group: &str) -> Vec<String> {
+        let mut references = Vec::new();
+
+        for other in self.groups.values() {
+            if other.subgroups.iter().any(|g| g == group) {
+                references.push(format!("nested in group '{}'", other.name));
+            }
+        }
+        for secret in self.secrets.values() {
+            if secret.permissions.groups.iter().any(|g| g == group) {
+                references.push(format!("permission on secret '{}'", secret.key));
+            }
+        }
+
+        references.sort();
+        references
+    }
+
+    /// Drops group memberships and secret permissions that name unknown users or groups.
+    /// Returns a description of each change.
+    pub fn repair_references(&mut self) -> Vec<String> {
+        let mut changes = Vec::new();
+        let users: BTreeSet<String> = self.users.keys().cloned().collect();
+        let groups: BTreeSet<String> = self.groups.keys().cloned().collect();
+
+        for group in self.groups.values_mut() {
+            for username in drain_unknown(&mut group.members, &users) {
+                changes.push(format!(
+                    "removed user '{}' from group '{}'",
+                    username, group.name
+                ));
+            }
+            for username in drain_unknown(&mut group.managers, &users) {
+                changes.push(format!(
+                    "removed manager '{}' from group '{}'",
+                    username, group.name
+                ));
+            }
+            for subgroup in drain_unknown(&mut group.subgroups, &groups) {
+                changes.push(format!(
+                    "removed group '{}' from group '{}'",
+                    subgroup, group.name
+                ));
+            }
+        }
+
+        let admins: Vec<String> = self
+            .users
+            .values()
+            .filter(|u| u.is_admin)
+            .map(|u| u.username.clone())
+            .collect();
+
+        for secret in self.secrets.values_mut() {
+            let permissions = &mut secret.permissions;
+            let was_restricted = !permissions.users.is_empty() || !permissions.groups.is_empty();
+
+            for username in drain_unknown(&mut permissions.users, &users) {
+                changes.push(format!(
+                    "removed user '{}' from secret '{}'",
+                    username, secret.key
+                ));
+            }
+            for group in drain_unknown(&mut permissions.groups, &groups) {
+                changes.push(format!(
+                    "removed group '{}' from secret '{}'",
+                    group, secret.key
+                ));
+            }
+
+            // Empty permissions mean "everyone", so never widen access by cleaning up
+            if was_restricted && permissions.users.is_empty() && permissions.groups.is_empty() {
+                permissions.users = admins.clone();
+                permissions.users.sort();
+                changes.push(format!(
+                    "restricted secret '{}' to admins (no remaining permissions)",
+                    secret.key
+                ));
+            }
+        }
+
+        changes.sort();
+        changes
+    }
+
+    pub fn can_access(&self, secret: &Secret, username: &str) -> bool {
+        let permissions = &secret.permissions;
+
+        permissions.users.is_empty() && permissions.groups.is_empty()
+            || permissions.users.iter().any(|u| u == username)
+            || permissions
+                .groups
+                .iter()
+                .any(|group| self.effective_members(group).contains(username))
+    }
+}
+
 // Local credential cache - stored locally, never committed
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct LocalCredentials {
@@ -29,29 +261,76 @@ pub struct LocalCredentials {
     pub username: Option<String>,
     pub master_key: Option<String>,
     pub is_admin: bool,
+    #[serde(default)]
+    pub vault_public_key: Option<String>, // Pinned on first load so the signature can't be stripped
+    #[serde(default)]
+    pub vault_revision: u64, // Highest vault revision loaded on this machine
+    #[serde(default)]
+    pub audit_entries: usize, // Audit log length last written or verified on this machine
+    #[serde(default)]
+    pub audit_head: Option<String>, // Fingerprint of the last of those entries
+    #[serde(default)]
+    pub membership_counters: HashMap<Uuid, u64>, // Highest membership signature seen per delegated group
+}
+
+// Usage tracking - encrypted with master key, kept separate from the vault
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AccessLog {
+    pub users: HashMap<String, AccessRecord>,
+    pub secrets: HashMap<String, AccessRecord>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessRecord {
+    pub last_access: String,
+    pub last_user: String,
+    pub count: u64,
+}
+
+// One line of the audit log - each entry is encrypted on its own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub timestamp: String,
+    pub user: String,
+    pub host: String,
+    pub action: String,
+    pub key: Option<String>,
+    pub details: Option<String>,
+    pub prev_hash: String, // SHA-256 of the previous entry, chaining the log
+}
+
+// Onboarding bundle - encrypted with a one-time passphrase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invite {
+    pub project_name: String,
+    pub username: String,
+    pub master_key: String,
+    pub token: String,
+    pub created_at: String,
+    pub expires_at: String,
+    #[serde(default)]
+    pub signing_key: Option<String>, // The user's membership signing key, until they pick a password
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct EncryptedData {
     pub salt: String, // Base64 encoded random salt
     pub data: String, // Base64 encoded encrypted data
 }
 
-impl Default for EncryptedData {
-    fn default() -> Self {
-        Self {
-            salt: String::new(),
-            data: String::new(),
-        }
-    }
-}
-
 impl EncryptedData {
     pub fn is_empty(&self) -> bool {
         self.salt.is_empty() && self.data.is_empty()
     }
 }
 
+fn drain_unknown(names: &mut Vec<String>, known: &BTreeSet<String>) -> Vec<String> {
+    let (kept, removed) = names.drain(..).partition(|name| known.contains(name));
+    *names = kept;
+    removed
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     pub id: Uuid,
@@ -62,6 +341,59 @@ pub struct User {
     pub created_at: String,
     pub last_access: Option<String>,
     pub is_admin: bool,
+    #[serde(default)]
+    pub invite_token_hash: Option<String>, // Set while an invitation is pending
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>, // Ed25519 key for signing membership of delegated groups
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<EncryptedData>, // Its private half, encrypted with the user's password
+}
+
+impl User {
+    pub fn is_expired(&self) -> bool {
+        match &self.expires_at {
+            // An unreadable expiry is treated as already expired
+            Some(expires_at) => DateTime::parse_from_rfc3339(expires_at)
+                .map(|t| t < Utc::now())
+                .unwrap_or(true),
+            None => false,
+        }
+    }
+
+    pub fn ensure_active(&self) -> Result<()> {
+        if self.disabled {
+            return Err(anyhow!(
+                "Account '{}' is suspended. Contact an admin.",
+                self.username
+            ));
+        }
+        if self.is_expired() {
+            return Err(anyhow!(
+                "Account '{}' expired on {}. Contact an admin.",
+                self.username,
+                self.expires_at.as_deref().unwrap_or("unknown date")
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn status(&self) -> String {
+        if self.disabled {
+            "suspended".to_string()
+        } else if self.is_expired() {
+            "expired".to_string()
+        } else if self.invite_token_hash.is_some() {
+            "invite pending".to_string()
+        } else if let Some(expires_at) = &self.expires_at {
+            format!("active until {}", expires_at)
+        } else {
+            "active".to_string()
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,7 +402,21 @@ pub struct Group {
     pub name: String,
     pub description: Option<String>,
     pub members: Vec<String>,
+    #[serde(default)]
+    pub subgroups: Vec<String>,
+    #[serde(default)]
+    pub managers: Vec<String>, // Users allowed to change membership without admin rights
     pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub membership_signature: Option<MembershipSignature>, // Set while the group is delegated
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MembershipSignature {
+    pub manager: Option<String>, // Signing manager; None for the admin key
+    pub signature: String,
+    #[serde(default)]
+    pub counter: u64, // Raised with every signature, so older member lists can't be replayed
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -83,6 +429,9 @@ pub struct Secret {
     pub permissions: Permissions,
     pub is_file: bool,
     pub file_path: Option<String>,
+    /// Labels for grouping secrets, e.g. in `apply` template loops
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1,7 +1,7 @@
 use colored::*;
 use dialoguer::{Confirm, Input, Password, Select};
 use indicatif::{ProgressBar, ProgressStyle};
-use std::io::{self, Write};
+use std::io;
 
 pub struct UI;
 
@@ -52,10 +52,12 @@ impl UI {
         Confirm::new().with_prompt(prompt).interact()
     }
 
+    #[allow(dead_code)]
     pub fn select(prompt: &str, items: &[&str]) -> Result<usize, io::Error> {
         Select::new().with_prompt(prompt).items(items).interact()
     }
 
+    #[allow(dead_code)]
     pub fn progress_bar(len: u64, msg: &str) -> ProgressBar {
         let pb = ProgressBar::new(len);
         pb.set_style(
@@ -0,0 +1,502 @@
+use crate::config::ConfigManager;
+use crate::crypto::CryptoManager;
+use crate::types::{EncryptedSecrets, Permissions, PrivateConfig, SecretValue, SmolcaseConfig};
+use anyhow::Result;
+use colored::*;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+
+/// A fully decrypted vault revision.
+pub struct VaultSnapshot {
+    pub public: SmolcaseConfig,
+    pub private: PrivateConfig,
+    pub values: HashMap<String, SecretValue>,
+}
+
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+pub struct VaultChange {
+    pub kind: ChangeKind,
+    pub description: String,
+    /// Value-free imperative form used in commit messages, e.g. "add STRIPE_KEY (users: alice)".
+    pub summary: String,
+}
+
+/// Key-level comparison of two vault revisions. Values stay hidden unless `reveal` is set.
+pub struct VaultDiff;
+
+impl VaultDiff {
+    pub fn snapshot(content: &str, master_key: &str) -> Result<VaultSnapshot> {
+        let public = ConfigManager::parse_config(content)?;
+        let private = ConfigManager::decrypt_private_config(&public, master_key)?;
+
+        let values = if private.encrypted_secrets.is_empty() {
+            HashMap::new()
+        } else {
+            let decrypted_data =
+                CryptoManager::decrypt_data_with_salt(&private.encrypted_secrets, master_key)?;
+            let secrets: EncryptedSecrets = serde_json::from_slice(&decrypted_data)?;
+            secrets
+                .secrets
+                .into_iter()
+                .map(|s| (s.key.clone(), s))
+                .collect()
+        };
+
+        Ok(VaultSnapshot {
+            public,
+            private,
+            values,
+        })
+    }
+
+    /// Snapshot of an optional revision; `None` means the vault file did not exist.
+    pub fn snapshot_of(content: Option<&str>, master_key: &str) -> Result<Option<VaultSnapshot>> {
+        content
+            .map(|content| Self::snapshot(content, master_key))
+            .transpose()
+    }
+
+    pub fn compare(
+        old: Option<&VaultSnapshot>,
+        new: Option<&VaultSnapshot>,
+        reveal: bool,
+    ) -> Vec<VaultChange> {
+        let mut changes = Vec::new();
+
+        if let (Some(old), Some(new)) = (old, new) {
+            if old.public.project_name != new.public.project_name {
+                changes.push(changed(
+                    format!(
+                        "project renamed '{}' → '{}'",
+                        old.public.project_name, new.public.project_name
+                    ),
+                    format!("rename project to {}", new.public.project_name),
+                ));
+            }
+            if old.public.admin_key_hash != new.public.admin_key_hash {
+                changes.push(changed(
+                    "admin password changed".to_string(),
+                    "change admin password".to_string(),
+                ));
+            }
+            if old.public.master_key_hash != new.public.master_key_hash {
+                changes.push(changed(
+                    "master key changed".to_string(),
+                    "rotate master key".to_string(),
+                ));
+            }
+            for ((key, old_value), (_, new_value)) in old
+                .public
+                .settings
+                .entries()
+                .into_iter()
+                .zip(new.public.settings.entries())
+            {
+                if old_value != new_value {
+                    changes.push(changed(
+                        format!("setting {}: {} → {}", key, old_value, new_value),
+                        format!("set {} = {}", key, new_value),
+                    ));
+                }
+            }
+        }
+
+        Self::compare_secrets(old, new, reveal, &mut changes);
+        Self::compare_users(old, new, &mut changes);
+        Self::compare_groups(old, new, &mut changes);
+
+        changes
+    }
+
+    /// Stable plain-text rendering of a vault, used as a git `textconv` filter.
+    /// Without `reveal`, values appear as fingerprints keyed by the master key, so
+    /// a changed value still shows up as a changed line.
+    pub fn render(snapshot: &VaultSnapshot, master_key: &str, reveal: bool) -> String {
+        let mut lines = vec![
+            format!("project: {}", snapshot.public.project_name),
+            format!(
+                "admin password: {}",
+                fingerprint(master_key, "admin", &snapshot.public.admin_key_hash)
+            ),
+        ];
+
+        let mut keys: Vec<_> = snapshot.private.secrets.keys().collect();
+        keys.sort();
+        for key in keys {
+            let secret = &snapshot.private.secrets[key];
+            let value = snapshot.values.get(key);
+            lines.push(String::new());
+            lines.push(format!("secret {}", key));
+            lines.push(format!(
+                "  access: {}",
+                describe_permissions(&secret.permissions)
+            ));
+            lines.push(format!(
+                "  value: {}",
+                if reveal {
+                    show_value(value)
+                } else {
+                    let raw = value.map(|v| match &v.file_content {
+                        Some(content) => String::from_utf8_lossy(content).to_string(),
+                        None => v.value.clone(),
+                    });
+                    fingerprint(master_key, key, raw.as_deref().unwrap_or(""))
+                }
+            ));
+        }
+
+        let mut users: Vec<_> = snapshot.private.users.values().collect();
+        users.sort_by(|a, b| a.username.cmp(&b.username));
+        for user in users {
+            lines.push(String::new());
+            lines.push(format!(
+                "user {}{}",
+                user.username,
+                if user.is_admin { " (admin)" } else { "" }
+            ));
+            lines.push(format!("  status: {}", user.status()));
+            if let Some(email) = &user.email {
+                lines.push(format!("  email: {}", email));
+            }
+            lines.push(format!(
+                "  password: {}",
+                fingerprint(master_key, &user.username, &user.password_hash)
+            ));
+        }
+
+        let mut groups: Vec<_> = snapshot.private.groups.keys().collect();
+        groups.sort();
+        for name in groups {
+            let group = &snapshot.private.groups[name];
+            lines.push(String::new());
+            lines.push(format!("group {}", name));
+            for (label, list) in [
+                ("members", &group.members),
+                ("subgroups", &group.subgroups),
+                ("managers", &group.managers),
+            ] {
+                if !list.is_empty() {
+                    let mut sorted = list.clone();
+                    sorted.sort();
+                    lines.push(format!("  {}: {}", label, sorted.join(", ")));
+                }
+            }
+        }
+
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// Commit message for a set of changes: a one-line subject, plus a body
+    /// listing every change when there is more than one. Never includes values.
+    pub fn commit_message(changes: &[VaultChange]) -> String {
+        match changes {
+            [] => "smolcase: update vault".to_string(),
+            [change] => format!("smolcase: {}", change.summary),
+            [first, rest @ ..] => {
+                let mut message = format!(
+                    "smolcase: {} and {} more change{}\n\n",
+                    first.summary,
+                    rest.len(),
+                    if rest.len() == 1 { "" } else { "s" }
+                );
+                for change in changes {
+                    message.push_str(&format!("- {}\n", change.summary));
+                }
+                message
+            }
+        }
+    }
+
+    pub fn print(changes: &[VaultChange], indent: &str) {
+        for change in changes {
+            let line = match change.kind {
+                ChangeKind::Added => format!("+ {}", change.description).green(),
+                ChangeKind::Removed => format!("- {}", change.description).red(),
+                ChangeKind::Changed => format!("~ {}", change.description).yellow(),
+            };
+            println!("{}{}", indent, line);
+        }
+    }
+
+    fn compare_secrets(
+        old: Option<&VaultSnapshot>,
+        new: Option<&VaultSnapshot>,
+        reveal: bool,
+        changes: &mut Vec<VaultChange>,
+    ) {
+        let empty = HashMap::new();
+        let old_secrets = old.map_or(&empty, |s| &s.private.secrets);
+        let new_secrets = new.map_or(&empty, |s| &s.private.secrets);
+        let old_value = |key: &str| old.and_then(|s| s.values.get(key));
+        let new_value = |key: &str| new.and_then(|s| s.values.get(key));
+
+        for key in union(old_secrets.keys(), new_secrets.keys()) {
+            match (old_secrets.get(key), new_secrets.get(key)) {
+                (None, Some(secret)) => {
+                    let access = describe_permissions(&secret.permissions);
+                    let mut description = format!("secret {} ({})", key, access);
+                    if reveal {
+                        description.push_str(&format!(" = {}", show_value(new_value(key))));
+                    }
+                    changes.push(added(description, format!("add {} ({})", key, access)));
+                }
+                (Some(_), None) => {
+                    let mut description = format!("secret {}", key);
+                    if reveal {
+                        description.push_str(&format!(" (was {})", show_value(old_value(key))));
+                    }
+                    changes.push(removed(description, format!("remove {}", key)));
+                }
+                (Some(old_secret), Some(new_secret)) => {
+                    if !same_value(old_value(key), new_value(key)) {
+                        let description = if reveal {
+                            format!(
+                                "secret {}: {} → {}",
+                                key,
+                                show_value(old_value(key)),
+                                show_value(new_value(key))
+                            )
+                        } else {
+                            format!("secret {} value changed", key)
+                        };
+                        changes.push(changed(description, format!("update {}", key)));
+                    }
+
+                    let mut deltas = Vec::new();
+                    if let Some(delta) =
+                        list_delta(&old_secret.permissions.users, &new_secret.permissions.users)
+                    {
+                        deltas.push(format!("users {}", delta));
+                    }
+                    if let Some(delta) = list_delta(
+                        &old_secret.permissions.groups,
+                        &new_secret.permissions.groups,
+                    ) {
+                        deltas.push(format!("groups {}", delta));
+                    }
+                    if !deltas.is_empty() {
+                        changes.push(changed(
+                            format!("secret {} permissions: {}", key, deltas.join("; ")),
+                            format!("change {} access ({})", key, deltas.join("; ")),
+                        ));
+                    }
+                    if let Some(delta) = list_delta(&old_secret.tags, &new_secret.tags) {
+                        changes.push(changed(
+                            format!("secret {} tags {}", key, delta),
+                            format!("tag {} ({})", key, delta),
+                        ));
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn compare_users(
+        old: Option<&VaultSnapshot>,
+        new: Option<&VaultSnapshot>,
+        changes: &mut Vec<VaultChange>,
+    ) {
+        let empty = HashMap::new();
+        let old_users = old.map_or(&empty, |s| &s.private.users);
+        let new_users = new.map_or(&empty, |s| &s.private.users);
+
+        for name in union(old_users.keys(), new_users.keys()) {
+            match (old_users.get(name), new_users.get(name)) {
+                (None, Some(user)) => changes.push(added(
+                    format!(
+                        "user {}{}",
+                        name,
+                        if user.is_admin { " (admin)" } else { "" }
+                    ),
+                    format!("add user {}", name),
+                )),
+                (Some(_), None) => changes.push(removed(
+                    format!("user {}", name),
+                    format!("remove user {}", name),
+                )),
+                (Some(old_user), Some(new_user)) => {
+                    let mut details = Vec::new();
+                    if old_user.password_hash != new_user.password_hash {
+                        details.push("password changed".to_string());
+                    }
+                    if old_user.is_admin != new_user.is_admin {
+                        details.push(if new_user.is_admin {
+                            "made admin".to_string()
+                        } else {
+                            "admin removed".to_string()
+                        });
+                    }
+                    if old_user.disabled != new_user.disabled {
+                        details.push(if new_user.disabled {
+                            "suspended".to_string()
+                        } else {
+                            "resumed".to_string()
+                        });
+                    }
+                    if old_user.expires_at != new_user.expires_at {
+                        details.push(format!(
+                            "expiry {}",
+                            new_user.expires_at.as_deref().unwrap_or("removed")
+                        ));
+                    }
+                    if old_user.invite_token_hash.is_none() && new_user.invite_token_hash.is_some()
+                    {
+                        details.push("invited".to_string());
+                    }
+                    if old_user.email != new_user.email {
+                        details.push("email changed".to_string());
+                    }
+                    if !details.is_empty() {
+                        changes.push(changed(
+                            format!("user {}: {}", name, details.join(", ")),
+                            format!("update user {} ({})", name, details.join(", ")),
+                        ));
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn compare_groups(
+        old: Option<&VaultSnapshot>,
+        new: Option<&VaultSnapshot>,
+        changes: &mut Vec<VaultChange>,
+    ) {
+        let empty = HashMap::new();
+        let old_groups = old.map_or(&empty, |s| &s.private.groups);
+        let new_groups = new.map_or(&empty, |s| &s.private.groups);
+
+        for name in union(old_groups.keys(), new_groups.keys()) {
+            match (old_groups.get(name), new_groups.get(name)) {
+                (None, Some(group)) => {
+                    let mut description = format!("group {}", name);
+                    if !group.members.is_empty() {
+                        description.push_str(&format!(" (members: {})", group.members.join(", ")));
+                    }
+                    changes.push(added(description, format!("create group {}", name)));
+                }
+                (Some(_), None) => changes.push(removed(
+                    format!("group {}", name),
+                    format!("delete group {}", name),
+                )),
+                (Some(old_group), Some(new_group)) => {
+                    let mut deltas = Vec::new();
+                    if let Some(delta) = list_delta(&old_group.members, &new_group.members) {
+                        deltas.push(format!("members {}", delta));
+                    }
+                    if let Some(delta) = list_delta(&old_group.subgroups, &new_group.subgroups) {
+                        deltas.push(format!("subgroups {}", delta));
+                    }
+                    if let Some(delta) = list_delta(&old_group.managers, &new_group.managers) {
+                        deltas.push(format!("managers {}", delta));
+                    }
+                    if !deltas.is_empty() {
+                        changes.push(changed(
+                            format!("group {}: {}", name, deltas.join("; ")),
+                            format!("update group {} ({})", name, deltas.join("; ")),
+                        ));
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+fn added(description: String, summary: String) -> VaultChange {
+    VaultChange {
+        kind: ChangeKind::Added,
+        description,
+        summary,
+    }
+}
+
+fn removed(description: String, summary: String) -> VaultChange {
+    VaultChange {
+        kind: ChangeKind::Removed,
+        description,
+        summary,
+    }
+}
+
+fn changed(description: String, summary: String) -> VaultChange {
+    VaultChange {
+        kind: ChangeKind::Changed,
+        description,
+        summary,
+    }
+}
+
+fn union<'a>(
+    a: impl Iterator<Item = &'a String>,
+    b: impl Iterator<Item = &'a String>,
+) -> BTreeSet<&'a String> {
+    a.chain(b).collect()
+}
+
+fn list_delta(old: &[String], new: &[String]) -> Option<String> {
+    let mut parts: Vec<String> = new
+        .iter()
+        .filter(|n| !old.contains(n))
+        .map(|n| format!("+{}", n))
+        .collect();
+    parts.extend(
+        old.iter()
+            .filter(|o| !new.contains(o))
+            .map(|o| format!("-{}", o)),
+    );
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(", "))
+    }
+}
+
+fn describe_permissions(permissions: &Permissions) -> String {
+    if permissions.users.is_empty() && permissions.groups.is_empty() {
+        return "all users".to_string();
+    }
+
+    let mut parts = Vec::new();
+    if !permissions.users.is_empty() {
+        parts.push(format!("users: {}", permissions.users.join(", ")));
+    }
+    if !permissions.groups.is_empty() {
+        parts.push(format!("groups: {}", permissions.groups.join(", ")));
+    }
+    parts.join(", ")
+}
+
+fn same_value(old: Option<&SecretValue>, new: Option<&SecretValue>) -> bool {
+    match (old, new) {
+        (Some(old), Some(new)) => old.value == new.value && old.file_content == new.file_content,
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+fn show_value(value: Option<&SecretValue>) -> String {
+    match value {
+        Some(value) if value.is_file => format!(
+            "<file, {} bytes>",
+            value.file_content.as_ref().map_or(0, |c| c.len())
+        ),
+        Some(value) => format!("{:?}", value.value),
+        None => "<missing>".to_string(),
+    }
+}
+
+fn fingerprint(master_key: &str, label: &str, value: &str) -> String {
+    let digest = Sha256::digest(format!("{}\0{}\0{}", master_key, label, value));
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", &hex[..12])
+}
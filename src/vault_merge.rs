@@ -0,0 +1,327 @@
+use crate::config::ConfigManager;
+use crate::credential_manager::CredentialManager;
+use crate::crypto::CryptoManager;
+use crate::signing::VaultSigner;
+use crate::types::{EncryptedData, EncryptedSecrets, PrivateConfig};
+use crate::vault_diff::VaultDiff;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+/// Three-way merge of diverged vault files.
+///
+/// Both sides are decrypted and merged entry by entry (secrets, values, users,
+/// groups). When both sides changed the same entry differently, the local
+/// version wins and a note is returned so the caller can report it.
+pub struct VaultMerge;
+
+impl VaultMerge {
+    pub fn merge(
+        base: Option<&str>,
+        ours: &str,
+        theirs: &str,
+        master_key: &str,
+    ) -> Result<(String, Vec<String>)> {
+        let base = VaultDiff::snapshot_of(base, master_key)?;
+        let ours = VaultDiff::snapshot(ours, master_key)?;
+        let theirs = VaultDiff::snapshot(theirs, master_key)?;
+        let mut notes = Vec::new();
+
+        let secrets = merge_map(
+            "secret",
+            base.as_ref().map(|b| &b.private.secrets),
+            &ours.private.secrets,
+            &theirs.private.secrets,
+            &mut notes,
+        )?;
+        let values = merge_map(
+            "value of",
+            base.as_ref().map(|b| &b.values),
+            &ours.values,
+            &theirs.values,
+            &mut notes,
+        )?;
+        let users = merge_map(
+            "user",
+            base.as_ref().map(|b| &b.private.users),
+            &ours.private.users,
+            &theirs.private.users,
+            &mut notes,
+        )?;
+        let groups = merge_map(
+            "group",
+            base.as_ref().map(|b| &b.private.groups),
+            &ours.private.groups,
+            &theirs.private.groups,
+            &mut notes,
+        )?;
+
+        let mut public = ours.public.clone();
+        public.revision = ours.public.revision.max(theirs.public.revision) + 1;
+        let base_public = base.as_ref().map(|b| &b.public);
+        if base_public.is_some_and(|b| b.admin_key_hash == ours.public.admin_key_hash) {
+            public.admin_key_hash = theirs.public.admin_key_hash.clone();
+        }
+        if base_public.is_some_and(|b| b.project_name == ours.public.project_name) {
+            public.project_name = theirs.public.project_name.clone();
+        }
+        if let Some(base_public) = base_public
+            && same(Some(&base_public.settings), Some(&ours.public.settings))?
+        {
+            public.settings = theirs.public.settings.clone();
+        }
+        if let Some(base_public) = base_public
+            && same(
+                Some(&base_public.admin_signing_key),
+                Some(&ours.public.admin_signing_key),
+            )?
+        {
+            public.admin_public_key = theirs.public.admin_public_key.clone();
+            public.admin_signing_key = theirs.public.admin_signing_key.clone();
+        }
+
+        let mut values: Vec<_> = values.into_values().collect();
+        values.sort_by(|a, b| a.key.cmp(&b.key));
+        // Keep only values that still have metadata, and the other way round
+        values.retain(|v| secrets.contains_key(&v.key));
+
+        let encrypted_secrets = if values.is_empty() {
+            EncryptedData::default()
+        } else {
+            let data = serde_json::to_vec(&EncryptedSecrets { secrets: values })?;
+            CryptoManager::encrypt_data_with_salt(&data, master_key)?
+        };
+
+        let mut private = PrivateConfig {
+            users,
+            groups,
+            secrets,
+            encrypted_secrets,
+        };
+        notes.extend(private.repair_references());
+
+        // The new revision needs a fresh signature
+        if VaultSigner::is_signed(&public) {
+            let cached_creds = CredentialManager::load_credentials()?;
+            let admin_password = CredentialManager::get_admin_password(&cached_creds)?;
+            VaultSigner::sign(&mut public, &mut private, &admin_password)?;
+        }
+
+        let content = ConfigManager::render_config(&public, &private, master_key)?;
+        Ok((content, notes))
+    }
+}
+
+fn merge_map<T: Clone + Serialize>(
+    kind: &str,
+    base: Option<&HashMap<String, T>>,
+    ours: &HashMap<String, T>,
+    theirs: &HashMap<String, T>,
+    notes: &mut Vec<String>,
+) -> Result<HashMap<String, T>> {
+    let names: BTreeSet<&String> = ours.keys().chain(theirs.keys()).collect();
+    let mut merged = HashMap::new();
+
+    for name in names {
+        let base = base.and_then(|b| b.get(name));
+        let ours = ours.get(name);
+        let theirs = theirs.get(name);
+
+        let pick = if same(ours, theirs)? || same(base, theirs)? {
+            ours
+        } else if same(base, ours)? {
+            theirs
+        } else {
+            notes.push(format!(
+                "Both sides changed {} {}; kept the local version",
+                kind, name
+            ));
+            ours
+        };
+
+        if let Some(value) = pick {
+            merged.insert(name.clone(), value.clone());
+        }
+    }
+
+    Ok(merged)
+}
+
+fn same<T: Serialize>(a: Option<&T>, b: Option<&T>) -> Result<bool> {
+    Ok(serde_json::to_value(a)? == serde_json::to_value(b)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Permissions, Secret, SecretValue, SmolcaseConfig};
+    use uuid::Uuid;
+
+    const MASTER_KEY: &str = "test-master-key";
+
+    fn map(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn merged(
+        base: Option<&[(&str, &str)]>,
+        ours: &[(&str, &str)],
+        theirs: &[(&str, &str)],
+    ) -> (Vec<(String, String)>, Vec<String>) {
+        let mut notes = Vec::new();
+        let base = base.map(map);
+        let result = merge_map(
+            "secret",
+            base.as_ref(),
+            &map(ours),
+            &map(theirs),
+            &mut notes,
+        )
+        .unwrap();
+
+        let mut entries: Vec<_> = result.into_iter().collect();
+        entries.sort();
+        (entries, notes)
+    }
+
+    fn entries(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn takes_changes_from_either_side() {
+        let base = [("A", "1"), ("B", "1")];
+        let (result, notes) = merged(
+            Some(&base),
+            &[("A", "2"), ("B", "1")],
+            &[("A", "1"), ("B", "3")],
+        );
+
+        assert_eq!(result, entries(&[("A", "2"), ("B", "3")]));
+        assert!(notes.is_empty());
+    }
+
+    #[test]
+    fn keeps_additions_and_deletions() {
+        let base = [("A", "1"), ("B", "1")];
+        let (result, notes) = merged(
+            Some(&base),
+            &[("A", "1"), ("B", "1"), ("C", "1")],
+            &[("A", "1"), ("D", "1")],
+        );
+
+        assert_eq!(result, entries(&[("A", "1"), ("C", "1"), ("D", "1")]));
+        assert!(notes.is_empty());
+    }
+
+    #[test]
+    fn conflicting_changes_keep_ours_with_a_note() {
+        let base = [("A", "1")];
+        let (result, notes) = merged(Some(&base), &[("A", "2")], &[("A", "3")]);
+
+        assert_eq!(result, entries(&[("A", "2")]));
+        assert_eq!(
+            notes,
+            ["Both sides changed secret A; kept the local version"]
+        );
+
+        // Deleted here, changed there: also a conflict, and ours (the deletion) wins
+        let (result, notes) = merged(Some(&base), &[], &[("A", "3")]);
+        assert!(result.is_empty());
+        assert_eq!(notes.len(), 1);
+    }
+
+    #[test]
+    fn same_change_on_both_sides_is_not_a_conflict() {
+        let (result, notes) = merged(None, &[("A", "2")], &[("A", "2")]);
+
+        assert_eq!(result, entries(&[("A", "2")]));
+        assert!(notes.is_empty());
+    }
+
+    fn vault(revision: u64, values: &[(&str, &str)]) -> String {
+        let public = SmolcaseConfig {
+            version: "1.0.0".to_string(),
+            project_name: "merge-test".to_string(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            admin_key_hash: String::new(),
+            master_key_hash: CryptoManager::hash_password(MASTER_KEY).unwrap().0,
+            encrypted_data: EncryptedData::default(),
+            settings: Default::default(),
+            revision,
+            admin_public_key: None,
+            admin_signing_key: None,
+            signature: None,
+        };
+
+        // Fixed ids and times, so the same secret is equal in every vault
+        let secrets = values
+            .iter()
+            .map(|(key, _)| {
+                let secret = Secret {
+                    id: Uuid::from_u128(
+                        key.bytes().fold(0, |id, b| id.wrapping_mul(31) + b as u128),
+                    ),
+                    key: key.to_string(),
+                    created_at: "2026-01-01T00:00:00Z".to_string(),
+                    updated_at: "2026-01-01T00:00:00Z".to_string(),
+                    created_by: "admin".to_string(),
+                    permissions: Permissions {
+                        users: Vec::new(),
+                        groups: Vec::new(),
+                    },
+                    is_file: false,
+                    file_path: None,
+                    tags: Vec::new(),
+                };
+                (key.to_string(), secret)
+            })
+            .collect();
+        let values = values
+            .iter()
+            .map(|(key, value)| SecretValue {
+                key: key.to_string(),
+                value: value.to_string(),
+                is_file: false,
+                file_content: None,
+            })
+            .collect();
+
+        let private = PrivateConfig {
+            users: HashMap::new(),
+            groups: HashMap::new(),
+            secrets,
+            encrypted_secrets: CryptoManager::encrypt_data_with_salt(
+                &serde_json::to_vec(&EncryptedSecrets { secrets: values }).unwrap(),
+                MASTER_KEY,
+            )
+            .unwrap(),
+        };
+
+        ConfigManager::render_config(&public, &private, MASTER_KEY).unwrap()
+    }
+
+    #[test]
+    fn merges_diverged_vault_files() {
+        let base = vault(3, &[("API", "base"), ("DB", "base")]);
+        let ours = vault(4, &[("API", "ours"), ("DB", "base"), ("NEW", "ours")]);
+        let theirs = vault(5, &[("API", "base")]);
+
+        let (content, notes) = VaultMerge::merge(Some(&base), &ours, &theirs, MASTER_KEY).unwrap();
+        assert!(notes.is_empty(), "{:?}", notes);
+
+        let merged = VaultDiff::snapshot(&content, MASTER_KEY).unwrap();
+        assert_eq!(merged.public.revision, 6);
+        let mut keys: Vec<_> = merged.private.secrets.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, ["API", "NEW"]);
+        assert_eq!(merged.values["API"].value, "ours");
+        assert!(!merged.values.contains_key("DB"));
+    }
+}